@@ -0,0 +1,164 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use config::{ClockmodeConfigConfigfile, ConfigFile, DisplayMode};
+use utils::TimeUnit;
+
+/// The values of the window's widgets, as read when saving.
+#[derive(Debug, Clone)]
+pub struct Form {
+    pub target: String,
+    pub interval: i32,
+    pub precision: i32,
+    pub header: String,
+    pub footer: String,
+    pub header_fontsize: i32,
+    pub time_fontsize: i32,
+    pub footer_fontsize: i32,
+    pub window_title: String,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub unit: TimeUnit,
+    pub calendar_units: bool,
+    pub display_mode: DisplayMode,
+    pub breakdown_format: String,
+    pub clockmode: ClockmodeConfigConfigfile,
+}
+impl Form {
+    /// The config to save: every key the form has a widget for, and the rest as it was
+    /// in `loaded`, the file that was opened.
+    pub fn to_config(&self, loaded: &ConfigFile) -> ConfigFile {
+        ConfigFile {
+            version: config::CONFIG_VERSION,
+            target: self.target.clone(),
+            interval: self.interval,
+            precision: self.precision,
+            header: self.header.clone(),
+            footer: self.footer.clone(),
+            header_fontsize: self.header_fontsize,
+            time_fontsize: self.time_fontsize,
+            footer_fontsize: self.footer_fontsize,
+            window_title: self.window_title.clone(),
+            window_width: self.window_width,
+            window_height: self.window_height,
+            unit: self.unit,
+            calendar_units: self.calendar_units,
+            display_mode: self.display_mode,
+            breakdown_format: self.breakdown_format.clone(),
+            clockmode_settings: self.clockmode.clone(),
+            ..loaded.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{Direction, ExportFormat, FinishAction, Transition};
+
+    /// A file using every section the generator has no widgets for.
+    const LOADED: &str = "\
+version: 2
+target: 25m
+resume: true
+direction: auto
+timers:
+- target: 2026-12-31 23:59:00 Europe/Berlin
+  header: New year
+- target: PT90M
+  display_mode: breakdown
+  breakdown_format: '{h}:{m:02}:{s:02}'
+on_finish:
+  action: overdue
+  flash: true
+hooks:
+- remaining: 5
+  command: echo soon
+notifications:
+- remaining: 1
+  unit: h
+sounds:
+  finish: bell.ogg
+  milestones:
+  - remaining: 10
+    file: chime.ogg
+  volume: 0.5
+carousel_settings:
+  enable: true
+  transition: cut
+stopwatch_settings:
+  export_format: json
+";
+
+    fn form() -> Form {
+        Form {
+            target: "2026-12-31 23:59:00 Asia/Shanghai".to_string(),
+            interval: 250,
+            precision: 3,
+            header: "距离".to_string(),
+            footer: "还有".to_string(),
+            header_fontsize: 30,
+            time_fontsize: 60,
+            footer_fontsize: 20,
+            window_title: "ChronoTimer".to_string(),
+            window_width: 300,
+            window_height: 200,
+            unit: TimeUnit::Hour,
+            calendar_units: false,
+            display_mode: DisplayMode::Breakdown,
+            breakdown_format: "{d}d {h:02}:{m:02}:{s:02}".to_string(),
+            clockmode: ClockmodeConfigConfigfile {
+                enable: false,
+                fullscreen: true,
+                showsecond: false,
+                fontsize: 80,
+            },
+        }
+    }
+
+    #[test]
+    fn saved_config_reloads() {
+        for loaded in [
+            ConfigFile::default(),
+            ConfigFile::from_yaml(LOADED).unwrap(),
+        ] {
+            let config = form().to_config(&loaded);
+            config.validate().unwrap();
+            let yaml = config.to_yaml().unwrap();
+            let reloaded = ConfigFile::from_yaml(yaml.as_str()).unwrap();
+            assert_eq!(reloaded.to_yaml().unwrap(), yaml);
+        }
+    }
+
+    #[test]
+    fn saved_config_takes_the_form() {
+        let config = form().to_config(&ConfigFile::from_yaml(LOADED).unwrap());
+        assert_eq!(config.version, config::CONFIG_VERSION);
+        assert_eq!(config.target, "2026-12-31 23:59:00 Asia/Shanghai");
+        assert_eq!(config.interval, 250);
+        assert_eq!(config.unit, TimeUnit::Hour);
+        assert_eq!(config.display_mode, DisplayMode::Breakdown);
+        assert!(config.clockmode_settings.fullscreen);
+        assert_eq!(config.clockmode_settings.fontsize, 80);
+    }
+
+    #[test]
+    fn saved_config_keeps_the_loaded_sections() {
+        let loaded = ConfigFile::from_yaml(LOADED).unwrap();
+        let yaml = form().to_config(&loaded).to_yaml().unwrap();
+        let reloaded = ConfigFile::from_yaml(yaml.as_str()).unwrap();
+        assert!(reloaded.resume);
+        assert_eq!(reloaded.direction, Direction::Auto);
+        assert_eq!(reloaded.timers.len(), 2);
+        assert_eq!(reloaded.timers[1].target.as_deref(), Some("PT90M"));
+        assert_eq!(reloaded.on_finish.action, FinishAction::Overdue);
+        assert_eq!(reloaded.hooks[0].command, "echo soon");
+        assert_eq!(reloaded.notifications[0].unit, TimeUnit::Hour);
+        assert_eq!(reloaded.sounds.milestones[0].file, "chime.ogg");
+        assert_eq!(reloaded.carousel_settings.transition, Transition::Cut);
+        assert_eq!(
+            reloaded.stopwatch_settings.export_format,
+            ExportFormat::Json
+        );
+    }
+}
@@ -2,43 +2,59 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod form;
+
+use form::Form;
 use gtk::prelude::BuilderExtManual;
 use gtk::prelude::*;
 use gtk::{Builder, Button, Entry, FileChooserButton, Label, RadioButton, SpinButton, Window, CheckButton};
+use chrono::Local;
+use config::{ClockmodeConfigConfigfile, ConfigFile, DisplayMode};
 use serde;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
+use std::cell::RefCell;
 use std::rc::Rc;
-use utils::SplitedTime;
+use utils::{SplitedTime, Target, TimeUnit};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct ConfigFile {
-    target: String,
-    interval: i32,
-    precision: i32,
-    header: String,
-    footer: String,
-    header_fontsize: i32,
-    time_fontsize: i32,
-    footer_fontsize: i32,
-    window_title: String,
-    window_width: i32,
-    window_height: i32,
-    unit: String,
-    clockmode_settings: ClockmodeConfigConfigfile,
+/// Unit radio buttons in `ui/main.glade` and the unit each one stands for.
+const UNIT_RADIOS: [(&str, TimeUnit); 8] = [
+    ("msrd", TimeUnit::Millisecond),
+    ("srd", TimeUnit::Second),
+    ("mrd", TimeUnit::Minute),
+    ("hrd", TimeUnit::Hour),
+    ("drd", TimeUnit::Day),
+    ("wrd", TimeUnit::Week),
+    ("mord", TimeUnit::Month),
+    ("yrd", TimeUnit::Year),
+];
+
+/// Position of the radio button for `unit` in `UNIT_RADIOS`.
+fn radio_index(unit: TimeUnit) -> Option<usize> {
+    UNIT_RADIOS.iter().position(|(_, u)| *u == unit)
 }
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ClockmodeConfigConfigfile {
-    enable: bool,
-    fullscreen: bool,
-    showsecond: bool,
-    fontsize: i32,
+
+/// Which radio buttons, in the order of `UNIT_RADIOS`, are on once `unit` is selected.
+fn radios_for(unit: TimeUnit) -> [bool; UNIT_RADIOS.len()] {
+    let index = radio_index(unit);
+    std::array::from_fn(|i| Some(i) == index)
 }
+
+/// The unit of the first radio button that is on, or days if none is.
+fn selected_unit(active: impl IntoIterator<Item = bool>) -> TimeUnit {
+    active
+        .into_iter()
+        .zip(UNIT_RADIOS)
+        .find(|(active, _)| *active)
+        .map_or(TimeUnit::Day, |(_, (_, unit))| unit)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Lang {
     no_such_file: String,
     failed_to_parse_time: String,
-    invalid_time_unit: String,
+    invalid_time_zone: String,
     failed_to_read_file: String,
     failed_to_parse_config: String,
     failed_to_parse_input: String,
@@ -64,10 +80,20 @@ struct MainWindow {
     wintitle: Entry,
     winwidth: SpinButton,
     winhet: SpinButton,
+    tz: Entry,
     filec: FileChooserButton,
     openf: Button,
     savef: Button,
     radios: [RadioButton; 8],
+    calendar: CheckButton,
+    bd_enable: CheckButton,
+    bd_format: Entry,
+    preview: Label,
+    /// The opened file. Keys without a widget are saved back as they were.
+    loaded: Rc<RefCell<ConfigFile>>,
+    /// The opened target, saved as written until the date or zone is edited. The date
+    /// fields cannot hold a duration like `25m` or a relative date like `next friday`.
+    kept_target: Rc<RefCell<Option<String>>>,
     statusi: Label,
     cm_enable: CheckButton,
     cm_fs: CheckButton,
@@ -95,19 +121,21 @@ impl MainWindow {
             wintitle: builder.object("wintitle").unwrap(),
             winwidth: builder.object("winwidth").unwrap(),
             winhet: builder.object("winhet").unwrap(),
+            tz: builder.object("tz").unwrap(),
             filec: builder.object("filec").unwrap(),
             openf: builder.object("openf").unwrap(),
             savef: builder.object("savef").unwrap(),
-            radios: [
-                builder.object("msrd").unwrap(),
-                builder.object("srd").unwrap(),
-                builder.object("mrd").unwrap(),
-                builder.object("hrd").unwrap(),
-                builder.object("drd").unwrap(),
-                builder.object("wrd").unwrap(),
-                builder.object("mord").unwrap(),
-                builder.object("yrd").unwrap(),
-            ],
+            radios: UNIT_RADIOS.map(|(id, unit)| {
+                let radio: RadioButton = builder.object(id).unwrap();
+                radio.set_label(unit.label());
+                radio
+            }),
+            calendar: builder.object("calendar").unwrap(),
+            bd_enable: builder.object("bd_enable").unwrap(),
+            bd_format: builder.object("bd_format").unwrap(),
+            preview: builder.object("preview").unwrap(),
+            loaded: Rc::new(RefCell::new(ConfigFile::default())),
+            kept_target: Rc::new(RefCell::new(None)),
             statusi: builder.object("statusi").unwrap(),
             cm_enable: builder.object("cm_enable").unwrap(),
             cm_fs: builder.object("cm_fs").unwrap(),
@@ -115,8 +143,45 @@ impl MainWindow {
             cm_fonts: builder.object("cm_fonts").unwrap(),
         }
     }
-    fn get_selected_radio(&self) -> Option<RadioButton> {
-        self.radios.iter().find(|r| r.is_active()).cloned()
+    fn get_selected_unit(&self) -> TimeUnit {
+        selected_unit(self.radios.iter().map(|r| r.is_active()))
+    }
+    fn set_selected_unit(&self, unit: TimeUnit) {
+        for (radio, active) in self.radios.iter().zip(radios_for(unit)) {
+            if active {
+                radio.set_active(true);
+            }
+        }
+    }
+    /// Reads every widget that is saved.
+    fn form(&self) -> Form {
+        Form {
+            target: self.target(),
+            interval: self.itvl.value() as i32,
+            precision: self.prec.value() as i32,
+            header: self.header.text().to_string(),
+            footer: self.footer.text().to_string(),
+            header_fontsize: self.hfs.value() as i32,
+            time_fontsize: self.tfs.value() as i32,
+            footer_fontsize: self.ffs.value() as i32,
+            window_title: self.wintitle.text().to_string(),
+            window_width: self.winwidth.value() as i32,
+            window_height: self.winhet.value() as i32,
+            unit: self.get_selected_unit(),
+            calendar_units: self.calendar.is_active(),
+            display_mode: if self.bd_enable.is_active() {
+                DisplayMode::Breakdown
+            } else {
+                DisplayMode::Scalar
+            },
+            breakdown_format: self.bd_format.text().to_string(),
+            clockmode: ClockmodeConfigConfigfile {
+                enable: self.cm_enable.is_active(),
+                fullscreen: self.cm_fs.is_active(),
+                showsecond: self.cm_ss.is_active(),
+                fontsize: self.cm_fonts.value() as i32,
+            },
+        }
     }
     fn get_splitedtime(&self) -> SplitedTime {
         SplitedTime {
@@ -128,11 +193,44 @@ impl MainWindow {
             second: self.s.value() as i32,
         }
     }
+    /// The target to save: the opened one while it is kept, else the date fields and
+    /// the zone.
+    fn target(&self) -> String {
+        if let Some(target) = self.kept_target.borrow().clone() {
+            return target;
+        }
+        let target = SplitedTime::to_string(self.get_splitedtime());
+        let zone = self.tz.text().trim().to_string();
+        if zone.is_empty() {
+            target
+        } else {
+            format!("{} {}", target, zone)
+        }
+    }
     fn update_timecode(&self) {
         let mainwin = &self;
+        self.kept_target.replace(None);
         let splitedtime = self.get_splitedtime();
         let code = SplitedTime::to_string(splitedtime);
         mainwin.code.set_text(code.as_str());
+        self.update_preview();
+    }
+    fn update_preview(&self) {
+        let now = Local::now().fixed_offset();
+        let target = self.target().parse::<Target>().map(|t| t.resolve(now));
+        let breakdown = self.bd_format.text().parse::<utils::Breakdown>();
+        let text = match (target, breakdown) {
+            (Ok(target), Ok(breakdown)) => utils::format_breakdown(
+                now,
+                target,
+                &breakdown,
+                self.calendar.is_active(),
+                self.loaded.borrow().rounding,
+            ),
+            (Err(e), _) => e.to_string(),
+            (_, Err(e)) => e.to_string(),
+        };
+        self.preview.set_text(text.as_str());
     }
 }
 fn main() {
@@ -172,32 +270,42 @@ fn main() {
                     return;
                 }
             };
-            let config: ConfigFile = match serde_yaml::from_str(filecontent.as_str()) {
+            let config = match ConfigFile::from_yaml(filecontent.as_str()) {
                 Ok(a) => a,
-                Err(_) => {
-                    statusi.set_text(langconf_clone.failed_to_parse_config.as_str());
+                Err(e) => {
+                    statusi.set_text(
+                        format!("{}\n{}", langconf_clone.failed_to_parse_config, e).as_str(),
+                    );
                     return;
                 }
             };
-            let splitedtime = match SplitedTime::from_string(config.clone().target) {
+            let target = match config.target.parse::<Target>() {
                 Ok(a) => a,
                 Err(_) => {
                     statusi.set_text(langconf_clone.failed_to_parse_time.as_str());
                     return;
                 }
             };
+            mainwin_clone.kept_target.replace(None);
             let c = config.clone();
             println!("{:?}", c);
-            mainwin_clone.y.clone().set_value(splitedtime.year as f64);
-            mainwin_clone.mo.clone().set_value(splitedtime.month as f64);
-            mainwin_clone.d.clone().set_value(splitedtime.day as f64);
-            mainwin_clone.h.clone().set_value(splitedtime.hour as f64);
-            mainwin_clone.m.clone().set_value(splitedtime.minute as f64);
-            mainwin_clone.s.clone().set_value(splitedtime.second as f64);
-            mainwin_clone
-                .code
-                .clone()
-                .set_text(SplitedTime::to_string(splitedtime).as_str());
+            if let Target::At(_) = target {
+                let splitedtime = match SplitedTime::from_string(config.clone().target) {
+                    Ok(a) => a,
+                    Err(_) => {
+                        statusi.set_text(langconf_clone.failed_to_parse_time.as_str());
+                        return;
+                    }
+                };
+                let zone = utils::target_zone(config.target.as_str());
+                mainwin_clone.tz.set_text(zone.unwrap_or(""));
+                mainwin_clone.y.clone().set_value(splitedtime.year as f64);
+                mainwin_clone.mo.clone().set_value(splitedtime.month as f64);
+                mainwin_clone.d.clone().set_value(splitedtime.day as f64);
+                mainwin_clone.h.clone().set_value(splitedtime.hour as f64);
+                mainwin_clone.m.clone().set_value(splitedtime.minute as f64);
+                mainwin_clone.s.clone().set_value(splitedtime.second as f64);
+            }
             mainwin_clone.itvl.clone().set_value(config.interval as f64);
             println!("{}", config.precision);
             mainwin_clone.prec.clone().set_value(config.precision as f64);
@@ -226,33 +334,24 @@ fn main() {
             println!("{}", mainwin_clone.prec.value());
 
             println!("{}", mainwin_clone.prec.value());
-            let timeunit = config.unit;
-            if timeunit == "ms" {
-                mainwin_clone.radios[0].set_active(true);
-            } else if timeunit == "s" {
-                mainwin_clone.radios[1].set_active(true);
-            } else if timeunit == "m" {
-                mainwin_clone.radios[2].set_active(true);
-            } else if timeunit == "h" {
-                mainwin_clone.radios[3].set_active(true);
-            } else if timeunit == "d" {
-                mainwin_clone.radios[4].set_active(true);
-            } else if timeunit == "w" {
-                mainwin_clone.radios[5].set_active(true);
-            } else if timeunit == "mo" {
-                mainwin_clone.radios[6].set_active(true);
-            } else if timeunit == "y" {
-                mainwin_clone.radios[7].set_active(true);
-            } else {
-                statusi.set_text(langconf_clone.invalid_time_unit.as_str());
-                return;
-            }
+            mainwin_clone.set_selected_unit(config.unit);
+
+            mainwin_clone.calendar.set_active(config.calendar_units);
+            mainwin_clone
+                .bd_enable
+                .set_active(config.display_mode == DisplayMode::Breakdown);
+            mainwin_clone.bd_format.set_text(config.breakdown_format.as_str());
+            *mainwin_clone.loaded.borrow_mut() = config.clone();
 
             mainwin_clone.cm_enable.set_active(config.clockmode_settings.enable);
             mainwin_clone.cm_fs.set_active(config.clockmode_settings.fullscreen);
             mainwin_clone.cm_ss.set_active(config.clockmode_settings.showsecond);
             mainwin_clone.cm_fonts.set_value(config.clockmode_settings.fontsize as f64);
 
+            // Filling the fields above clears it, so keep it last.
+            mainwin_clone.kept_target.replace(Some(config.target.clone()));
+            mainwin_clone.code.set_text(config.target.as_str());
+            mainwin_clone.update_preview();
             println!("{}", mainwin_clone.prec.value());
         }
     });
@@ -305,75 +404,49 @@ fn main() {
         }
     });
 
+    mainwin_c1.tz.connect_changed({
+        let mainwin = mainwin.clone();
+        move |_| {
+            mainwin.kept_target.replace(None);
+            mainwin.update_preview();
+        }
+    });
+
+    mainwin_c1.calendar.connect_toggled({
+        let mainwin = mainwin.clone();
+        move |_| {
+            mainwin.update_preview();
+        }
+    });
+
+    mainwin_c1.bd_format.connect_changed({
+        let mainwin = mainwin.clone();
+        move |_| {
+            mainwin.update_preview();
+        }
+    });
+
     mainwin_c1.savef.connect_clicked({
         let mainwin_clone = mainwin.clone();
         let langconf_clone = langconf.clone();
         move |_| {
-            let splitedtime = mainwin_clone.get_splitedtime();
-            let target = SplitedTime::to_string(splitedtime);
-            let interval = mainwin_clone.itvl.value() as i32;
-            let precision = mainwin_clone.prec.value() as i32;
-            let header = mainwin_clone.header.text().to_string();
-            let footer = mainwin_clone.footer.text().to_string();
-            let hfs = mainwin_clone.hfs.value() as i32;
-            let tfs = mainwin_clone.tfs.value() as i32;
-            let ffs = mainwin_clone.ffs.value() as i32;
-            let wintitle = mainwin_clone.wintitle.text().to_string();
-            let winwidth = mainwin_clone.winwidth.value() as i32;
-            let winhet = mainwin_clone.winhet.value() as i32;
+            let zone = mainwin_clone.tz.text().trim().to_string();
+            let kept = mainwin_clone.kept_target.borrow().is_some();
+            if !kept && !zone.is_empty() && zone.parse::<utils::TargetZone>().is_err() {
+                mainwin_clone
+                    .statusi
+                    .set_text(langconf_clone.invalid_time_zone.as_str());
+                return;
+            }
             let statusi = mainwin_clone.statusi.clone();
-            let unitradios = mainwin_clone
-                .get_selected_radio()
-                .unwrap()
-                .buildable_name()
-                .unwrap();
-            let timeunit;
-            if unitradios == "msrd" {
-                timeunit = "ms";
-            } else if unitradios == "srd" {
-                timeunit = "s";
-            } else if unitradios == "mrd" {
-                timeunit = "m";
-            } else if unitradios == "hrd" {
-                timeunit = "h";
-            } else if unitradios == "drd" {
-                timeunit = "d";
-            } else if unitradios == "wrd" {
-                timeunit = "w";
-            } else if unitradios == "mord" {
-                timeunit = "m";
-            } else if unitradios == "yrd" {
-                timeunit = "y";
-            } else {
-                timeunit = "d";
+            let configfile = mainwin_clone
+                .form()
+                .to_config(&mainwin_clone.loaded.borrow());
+            if let Err(e) = configfile.validate() {
+                statusi.set_text(e.to_string().as_str());
+                return;
             }
-
-            let cm_enable = mainwin_clone.cm_enable.is_active();
-            let cm_fs = mainwin_clone.cm_fs.is_active();
-            let cm_ss = mainwin_clone.cm_ss.is_active();
-            let cm_fonts = mainwin_clone.cm_fonts.value() as i32;
-
-            let configfile = ConfigFile {
-                target,
-                interval,
-                precision,
-                header,
-                footer,
-                header_fontsize: hfs,
-                time_fontsize: tfs,
-                footer_fontsize: ffs,
-                window_title: wintitle,
-                window_width: winwidth,
-                window_height: winhet,
-                unit: timeunit.to_string(),
-                clockmode_settings: ClockmodeConfigConfigfile {
-                    enable: cm_enable,
-                    fullscreen: cm_fs,
-                    showsecond: cm_ss,
-                    fontsize: cm_fonts,
-                }
-            };
-            let confile_text = match serde_yaml::to_string(&configfile) {
+            let confile_text = match configfile.to_yaml() {
                 Ok(a) => a,
                 Err(_) => {
                     statusi.set_text(langconf.failed_to_parse_input.as_str());
@@ -398,6 +471,63 @@ fn main() {
             };
         }
     });
+    let config_path = config::find_config_file(None);
+    if config_path.exists() {
+        mainwin.filec.set_filename(&config_path);
+    } else if let Some(dir) = config_path.parent().filter(|d| d.exists()) {
+        mainwin.filec.set_current_folder(dir);
+    }
+
+    mainwin.update_preview();
     mainwin.main_window.show_all();
     gtk::main();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radios_map_to_their_units_both_ways() {
+        let expected = [
+            ("msrd", TimeUnit::Millisecond),
+            ("srd", TimeUnit::Second),
+            ("mrd", TimeUnit::Minute),
+            ("hrd", TimeUnit::Hour),
+            ("drd", TimeUnit::Day),
+            ("wrd", TimeUnit::Week),
+            ("mord", TimeUnit::Month),
+            ("yrd", TimeUnit::Year),
+        ];
+        for (id, unit) in expected {
+            let by_id = UNIT_RADIOS.iter().find(|(i, _)| *i == id).map(|(_, u)| *u);
+            assert_eq!(by_id, Some(unit), "{}", id);
+            let by_unit = radio_index(unit).map(|i| UNIT_RADIOS[i].0);
+            assert_eq!(by_unit, Some(id), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn selecting_a_unit_reads_it_back() {
+        for unit in TimeUnit::ALL {
+            let active = radios_for(unit);
+            assert_eq!(active.iter().filter(|a| **a).count(), 1, "{}", unit);
+            assert_eq!(selected_unit(active), unit);
+        }
+    }
+
+    #[test]
+    fn no_active_radio_selects_days() {
+        assert_eq!(selected_unit([false; UNIT_RADIOS.len()]), TimeUnit::Day);
+        assert_eq!(selected_unit([]), TimeUnit::Day);
+    }
+
+    #[test]
+    fn radios_exist_in_the_ui() {
+        let glade = include_str!("../ui/main.glade");
+        for (id, _) in UNIT_RADIOS {
+            let object = format!("<object class=\"GtkRadioButton\" id=\"{}\">", id);
+            assert!(glade.contains(object.as_str()), "{}", id);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::ConfigError;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use utils::TimerState;
+
+/// The deadline a duration target resolved to, so a restarted timer can resume it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedDeadline {
+    /// The target as written in the config, e.g. `25m`.
+    pub target: String,
+    /// RFC 3339.
+    pub deadline: String,
+    /// A paused timer resumes paused, with as much time left as it had when saved.
+    #[serde(default)]
+    pub state: TimerState,
+}
+
+/// Deadlines of the duration timers, in the order of the timers, saved next to the
+/// config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedDeadlines {
+    /// When the deadlines were saved, RFC 3339.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<String>,
+    pub timers: Vec<Option<SavedDeadline>>,
+}
+impl SavedDeadlines {
+    /// `config.yaml.deadlines` next to `config.yaml`.
+    pub fn path(config_path: &Path) -> PathBuf {
+        let mut path = config_path.as_os_str().to_owned();
+        path.push(".deadlines");
+        PathBuf::from(path)
+    }
+
+    /// Reads the saved deadlines. A missing or unreadable file has none.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str(content.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        Ok(std::fs::write(path, serde_yaml::to_string(self)?)?)
+    }
+
+    /// The deadline saved for timer `index`, if it was saved for the same `target`.
+    pub fn get(&self, index: usize, target: &str) -> Option<&SavedDeadline> {
+        match self.timers.get(index) {
+            Some(Some(saved)) if saved.target == target => Some(saved),
+            _ => None,
+        }
+    }
+
+    /// Where timer `index` ends when restarted at `now`, and whether it was paused.
+    /// A running timer keeps its saved deadline, and a paused one gets the time it had
+    /// left when saved. Deadlines for another target, or that cannot be read, are
+    /// `None`.
+    pub fn resume(
+        &self,
+        index: usize,
+        target: &str,
+        now: DateTime<FixedOffset>,
+    ) -> Option<(DateTime<FixedOffset>, TimerState)> {
+        let saved = self.get(index, target)?;
+        let deadline = DateTime::parse_from_rfc3339(saved.deadline.as_str()).ok()?;
+        let deadline = match saved.state {
+            TimerState::Running => deadline,
+            TimerState::Paused => {
+                let saved_at = DateTime::parse_from_rfc3339(self.saved_at.as_deref()?).ok()?;
+                now + (deadline - saved_at)
+            }
+        };
+        Some((deadline, saved.state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(time).unwrap()
+    }
+
+    fn saved(deadline: &str, state: TimerState) -> Option<SavedDeadline> {
+        Some(SavedDeadline {
+            target: "25m".to_string(),
+            deadline: deadline.to_string(),
+            state,
+        })
+    }
+
+    #[test]
+    fn resume_keeps_running_deadlines_and_the_time_left_of_paused_ones() {
+        let saved = SavedDeadlines {
+            saved_at: Some("2026-12-31T12:00:00+08:00".to_string()),
+            timers: vec![
+                saved("2026-12-31T12:20:00+08:00", TimerState::Running),
+                None,
+                saved("2026-12-31T12:05:00+08:00", TimerState::Paused),
+            ],
+        };
+        let now = at("2026-12-31T15:00:00+08:00");
+        assert_eq!(
+            saved.resume(0, "25m", now),
+            Some((at("2026-12-31T12:20:00+08:00"), TimerState::Running))
+        );
+        assert_eq!(saved.resume(1, "25m", now), None);
+        assert_eq!(
+            saved.resume(2, "25m", now),
+            Some((at("2026-12-31T15:05:00+08:00"), TimerState::Paused))
+        );
+        // Saved for another target.
+        assert_eq!(saved.resume(0, "30m", now), None);
+        assert_eq!(saved.resume(3, "25m", now), None);
+    }
+
+    #[test]
+    fn saved_deadlines_reload() {
+        let saved = SavedDeadlines {
+            saved_at: Some("2026-12-31T12:00:00+08:00".to_string()),
+            timers: vec![None, saved("2026-12-31T12:05:00+08:00", TimerState::Paused)],
+        };
+        let yaml = serde_yaml::to_string(&saved).unwrap();
+        assert!(yaml.contains("state: paused"), "{yaml}");
+        assert_eq!(
+            serde_yaml::from_str::<SavedDeadlines>(yaml.as_str()).unwrap(),
+            saved
+        );
+    }
+
+    #[test]
+    fn files_without_states_are_running() {
+        let saved: SavedDeadlines =
+            serde_yaml::from_str("timers:\n- target: 25m\n  deadline: 2026-12-31T12:20:00+08:00\n")
+                .unwrap();
+        assert_eq!(saved.saved_at, None);
+        assert_eq!(
+            saved.resume(0, "25m", at("2026-12-31T12:00:00+08:00")),
+            Some((at("2026-12-31T12:20:00+08:00"), TimerState::Running))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+
+/// One problem found in a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Dotted path of the offending key, e.g. `clockmode_settings.fontsize`.
+    pub key: String,
+    /// 1-based line and column of the key in the file, when it could be found.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+impl Diagnostic {
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            position: None,
+            message: message.into(),
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// Deserializes every key of `mapping` into `T` on its own, so each type error can be
+/// pinned to its key. Failing keys, including ones `T` does not know, are reported and
+/// removed, leaving their defaults.
+pub(crate) fn check_keys<T: DeserializeOwned>(
+    mapping: &mut Mapping,
+    prefix: &str,
+    problems: &mut Vec<Diagnostic>,
+) {
+    let keys: Vec<Value> = mapping.keys().cloned().collect();
+    for key in keys {
+        let mut single = Mapping::new();
+        single.insert(key.clone(), mapping[&key].clone());
+        if let Err(e) = serde_yaml::from_value::<T>(Value::Mapping(single)) {
+            let name = match &key {
+                Value::String(name) => name.clone(),
+                other => format!("{:?}", other),
+            };
+            problems.push(Diagnostic::new(
+                format!("{}{}", prefix, name),
+                e.to_string(),
+            ));
+            mapping.remove(&key);
+        }
+    }
+}
+
+/// Finds the line and column where the dotted `key` is written in block-style YAML.
+/// List items are addressed by index, e.g. `timers.1.target`.
+pub(crate) fn locate(source: &str, key: &str) -> Option<(usize, usize)> {
+    struct Frame {
+        indent: usize,
+        segment: String,
+        is_item: bool,
+        items: usize,
+    }
+    let mut stack: Vec<Frame> = Vec::new();
+    let path = |stack: &[Frame]| {
+        stack
+            .iter()
+            .map(|f| f.segment.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+
+    for (number, line) in source.lines().enumerate() {
+        let mut rest = line.trim_start();
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+        let mut indent = line.len() - rest.len();
+        while let Some(item) = rest
+            .strip_prefix('-')
+            .filter(|r| r.is_empty() || r.starts_with(' '))
+        {
+            while stack
+                .last()
+                .is_some_and(|f| f.indent > indent || (f.indent == indent && f.is_item))
+            {
+                stack.pop();
+            }
+            let index = stack.last_mut().map_or(0, |parent| {
+                parent.items += 1;
+                parent.items - 1
+            });
+            stack.push(Frame {
+                indent,
+                segment: index.to_string(),
+                is_item: true,
+                items: 0,
+            });
+            if path(&stack) == key {
+                return Some((number + 1, indent + 1));
+            }
+            rest = item.trim_start();
+            indent = line.len() - rest.len();
+        }
+        let Some((name, _)) = rest.split_once(':') else {
+            continue;
+        };
+        while stack.last().is_some_and(|f| f.indent >= indent) {
+            stack.pop();
+        }
+        stack.push(Frame {
+            indent,
+            segment: name.trim().trim_matches(['"', '\'']).to_string(),
+            is_item: false,
+            items: 0,
+        });
+        if path(&stack) == key {
+            return Some((number + 1, indent + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+version: 2
+# comment: not a key
+target: 25m
+clockmode_settings:
+  fontsize: 100
+  showsecond: true
+timers:
+- target: 2026-12-31
+  header: first
+-   target: PT90M
+    footer: second
+- header: third
+sounds:
+  milestones:
+    - remaining: 10
+      file: a.ogg
+    - file: b.ogg
+'quoted': 1
+";
+
+    #[test]
+    fn locate_finds_keys() {
+        let cases = [
+            ("version", Some((1, 1))),
+            ("target", Some((3, 1))),
+            ("clockmode_settings", Some((4, 1))),
+            ("clockmode_settings.showsecond", Some((6, 3))),
+            ("timers", Some((7, 1))),
+            ("timers.0", Some((8, 1))),
+            ("timers.0.target", Some((8, 3))),
+            ("timers.0.header", Some((9, 3))),
+            ("timers.1.target", Some((10, 5))),
+            ("timers.1.footer", Some((11, 5))),
+            ("timers.2.header", Some((12, 3))),
+            ("sounds.milestones.0.remaining", Some((15, 7))),
+            ("sounds.milestones.1.file", Some((17, 7))),
+            ("quoted", Some((18, 1))),
+        ];
+        for (key, position) in cases {
+            assert_eq!(locate(SOURCE, key), position, "{}", key);
+        }
+    }
+
+    #[test]
+    fn locate_misses_absent_keys() {
+        for key in [
+            "comment",
+            "fontsize",
+            "timers.3",
+            "timers.0.footer",
+            "sounds.milestones.2.file",
+            "clockmode_settings.fontsize.x",
+        ] {
+            assert_eq!(locate(SOURCE, key), None, "{}", key);
+        }
+    }
+
+    #[derive(serde::Deserialize, Debug)]
+    #[serde(default, deny_unknown_fields)]
+    struct Section {
+        size: i32,
+        name: String,
+    }
+    impl Default for Section {
+        fn default() -> Self {
+            Self {
+                size: 7,
+                name: "default".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn check_keys_reports_and_removes_each_bad_key() {
+        let mut mapping: Mapping =
+            serde_yaml::from_str("size: big\nname: kept\nsise: 3\n").unwrap();
+        let mut problems = Vec::new();
+        check_keys::<Section>(&mut mapping, "section.", &mut problems);
+        let keys: Vec<&str> = problems.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["section.size", "section.sise"]);
+        assert!(problems[1].message.contains("unknown field `sise`"));
+        let section: Section = serde_yaml::from_value(Value::Mapping(mapping)).unwrap();
+        assert_eq!(section.size, 7);
+        assert_eq!(section.name, "kept");
+    }
+}
@@ -0,0 +1,976 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use diagnostic::check_keys;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use utils::{Rounding, TimeUnit};
+
+mod deadlines;
+mod diagnostic;
+mod paths;
+
+pub use deadlines::{SavedDeadline, SavedDeadlines};
+pub use diagnostic::Diagnostic;
+pub use paths::{CONFIG_FILE_NAME, find_config_file, portable_config_file, user_config_file};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("{}", join_diagnostics(.0))]
+    Invalid(Vec<Diagnostic>),
+}
+fn join_diagnostics(problems: &[Diagnostic]) -> String {
+    problems
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Version written by this build. Files without a `version` key are version 0.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version `n` file to version `n + 1`.
+const MIGRATIONS: [fn(&mut Mapping); CONFIG_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
+
+fn migrate_v0_to_v1(config: &mut Mapping) {
+    // Version 0 only knew 30-day months and 365-day years.
+    config
+        .entry("calendar_units".into())
+        .or_insert(Value::Bool(false));
+}
+
+fn migrate_v1_to_v2(config: &mut Mapping) {
+    // Version 1 rounded to the nearest value; new files default to `ceil`.
+    config
+        .entry("rounding".into())
+        .or_insert(Value::String("nearest".into()));
+}
+
+fn invalid(key: &str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid(vec![Diagnostic::new(key, message)])
+}
+
+/// Upgrades a parsed config file to `CONFIG_VERSION` and returns the version it had.
+pub fn migrate(config: &mut Value) -> Result<u32, ConfigError> {
+    if config.is_null() {
+        *config = Value::Mapping(Mapping::new());
+    }
+    let mapping = config
+        .as_mapping_mut()
+        .ok_or_else(|| invalid("", "the config file must be a mapping of keys to values"))?;
+    let version = match mapping.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| invalid("version", "must be a non-negative integer"))?,
+    };
+    if version > CONFIG_VERSION {
+        return Err(invalid(
+            "version",
+            format!(
+                "version {} is newer than the supported version {}",
+                version, CONFIG_VERSION
+            ),
+        ));
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        step(mapping);
+    }
+    mapping.insert("version".into(), CONFIG_VERSION.into());
+    Ok(version)
+}
+
+/// Upgrades an outdated config file in place, keeping the original next to it as
+/// `<name>.v<old version>.bak`. Returns the backup path if the file was upgraded.
+pub fn upgrade_file(path: impl AsRef<Path>) -> Result<Option<PathBuf>, ConfigError> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    let mut value: Value = serde_yaml::from_str(content.as_str())?;
+    let version = migrate(&mut value)?;
+    if version == CONFIG_VERSION {
+        return Ok(None);
+    }
+    let config: ConfigFile = serde_yaml::from_value(value)?;
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", version));
+    let backup = PathBuf::from(backup);
+    std::fs::copy(path, &backup)?;
+    config.save(path)?;
+    Ok(Some(backup))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub version: u32,
+    /// A date, or a duration like `25m` counted from when the timer starts.
+    pub target: String,
+    /// Resume duration targets from the deadline saved by the last run instead of
+    /// starting over.
+    pub resume: bool,
+    pub direction: Direction,
+    pub interval: i32,
+    pub precision: i32,
+    pub header: String,
+    pub footer: String,
+    pub header_fontsize: i32,
+    pub time_fontsize: i32,
+    pub footer_fontsize: i32,
+    pub window_title: String,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub unit: TimeUnit,
+    pub calendar_units: bool,
+    pub display_mode: DisplayMode,
+    pub breakdown_format: String,
+    pub rounding: Rounding,
+    pub refresh_mode: RefreshMode,
+    /// Countdowns shown as stacked rows. When empty, the top-level keys describe the
+    /// only countdown.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub timers: Vec<TimerConfig>,
+    pub on_finish: FinishConfig,
+    /// Shell commands run as countdowns approach their targets.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookConfig>,
+    /// Desktop notifications shown as countdowns approach their targets.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<NotificationConfig>,
+    pub sounds: SoundConfig,
+    pub carousel_settings: CarouselConfig,
+
+    pub clockmode_settings: ClockmodeConfigConfigfile,
+    pub stopwatch_settings: StopwatchConfig,
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Count down to `target`.
+    #[default]
+    Down,
+    /// Count up from `target`, showing the time elapsed since it.
+    Up,
+    /// Count down to `target`, then up once it has passed.
+    Auto,
+}
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(direction: &str) -> Result<Self, Self::Err> {
+        match direction {
+            "down" => Ok(Direction::Down),
+            "up" => Ok(Direction::Up),
+            "auto" => Ok(Direction::Auto),
+            _ => Err(format!("Invalid direction: '{}'", direction)),
+        }
+    }
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// A single number in `unit`.
+    #[default]
+    Scalar,
+    /// Several units laid out by `breakdown_format`.
+    Breakdown,
+}
+impl FromStr for DisplayMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "scalar" => Ok(DisplayMode::Scalar),
+            "breakdown" => Ok(DisplayMode::Breakdown),
+            _ => Err(format!("Invalid display mode: '{}'", mode)),
+        }
+    }
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshMode {
+    /// Update every `interval` milliseconds.
+    #[default]
+    Interval,
+    /// Update once per frame the window draws, ignoring `interval`.
+    Frame,
+}
+impl FromStr for RefreshMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "interval" => Ok(RefreshMode::Interval),
+            "frame" => Ok(RefreshMode::Frame),
+            _ => Err(format!("Invalid refresh mode: '{}'", mode)),
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockmodeConfigConfigfile {
+    pub enable: bool,
+    pub fullscreen: bool,
+    pub showsecond: bool,
+    pub fontsize: i32,
+}
+impl Default for ClockmodeConfigConfigfile {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            fullscreen: false,
+            showsecond: true,
+            fontsize: 100,
+        }
+    }
+}
+/// A stopwatch shown instead of the countdown, using the top-level `unit`,
+/// `precision` and `interval`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopwatchConfig {
+    pub enable: bool,
+    /// How many of the latest laps are listed under the time.
+    pub laps_shown: i32,
+    pub export_format: ExportFormat,
+    /// Directory laps are exported to; empty for the working directory.
+    pub export_dir: String,
+}
+impl Default for StopwatchConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            laps_shown: 3,
+            export_format: ExportFormat::Csv,
+            export_dir: String::new(),
+        }
+    }
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Invalid export format: '{}'", format)),
+        }
+    }
+}
+/// What happens when a countdown reaches its target. Timers counting up never finish;
+/// `auto` timers finish, flash and count towards `close`, but count up afterwards
+/// instead of showing what `action` would.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FinishConfig {
+    pub action: FinishAction,
+    /// Shown before the overdue time with `action: overdue`.
+    pub overdue_prefix: String,
+    /// Shown instead of the time with `action: message`.
+    pub message: String,
+    /// Blink the time and ask the window manager for attention.
+    pub flash: bool,
+}
+impl Default for FinishConfig {
+    fn default() -> Self {
+        Self {
+            action: FinishAction::Continue,
+            overdue_prefix: "超时 ".to_string(),
+            message: "时间到".to_string(),
+            flash: false,
+        }
+    }
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FinishAction {
+    /// Keep counting into negative numbers.
+    #[default]
+    Continue,
+    /// Stay at zero.
+    Stop,
+    /// Count the time since the target, prefixed with `overdue_prefix`.
+    Overdue,
+    /// Replace the time with `message`.
+    Message,
+    /// Close the window once every countdown has finished.
+    Close,
+}
+impl FromStr for FinishAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "continue" => Ok(FinishAction::Continue),
+            "stop" => Ok(FinishAction::Stop),
+            "overdue" => Ok(FinishAction::Overdue),
+            "message" => Ok(FinishAction::Message),
+            "close" => Ok(FinishAction::Close),
+            _ => Err(format!("Invalid finish action: '{}'", action)),
+        }
+    }
+}
+/// Something that happens once whenever a countdown's remaining time drops to a
+/// threshold, written as `remaining` `unit`s.
+pub trait Threshold {
+    fn threshold(&self) -> (i64, TimeUnit);
+
+    fn threshold_millis(&self) -> i64 {
+        let (remaining, unit) = self.threshold();
+        remaining.saturating_mul(unit.millis())
+    }
+}
+/// A shell command run once whenever a countdown's remaining time drops to
+/// `remaining` `unit`s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookConfig {
+    pub remaining: i64,
+    pub unit: TimeUnit,
+    pub command: String,
+    /// Seconds after which the command is killed.
+    pub timeout: i32,
+}
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            remaining: 0,
+            unit: TimeUnit::Minute,
+            command: String::new(),
+            timeout: 30,
+        }
+    }
+}
+impl Threshold for HookConfig {
+    fn threshold(&self) -> (i64, TimeUnit) {
+        (self.remaining, self.unit)
+    }
+}
+/// A desktop notification shown once whenever a countdown's remaining time drops to
+/// `remaining` `unit`s. `{header}`, `{footer}`, `{remaining}` and `{target}` in the
+/// title and body are replaced with the countdown's values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub remaining: i64,
+    pub unit: TimeUnit,
+    pub title: String,
+    pub body: String,
+}
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            remaining: 0,
+            unit: TimeUnit::Minute,
+            title: "{header}".to_string(),
+            body: "剩余 {remaining}".to_string(),
+        }
+    }
+}
+impl Threshold for NotificationConfig {
+    fn threshold(&self) -> (i64, TimeUnit) {
+        (self.remaining, self.unit)
+    }
+}
+/// Sound files played with GStreamer as countdowns approach and reach their targets.
+/// Files may be paths or URIs; an empty file plays nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundConfig {
+    /// Played when a countdown reaches its target.
+    pub finish: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub milestones: Vec<MilestoneSound>,
+    /// Played on every second of the last `tick_seconds` seconds.
+    pub tick: String,
+    pub tick_seconds: i64,
+    /// From 0.0 (silent) to 1.0 (full volume).
+    pub volume: f64,
+    /// GStreamer audio sink to play through, e.g. `fakesink` to play nothing.
+    pub sink: String,
+}
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            finish: String::new(),
+            milestones: Vec::new(),
+            tick: String::new(),
+            tick_seconds: 0,
+            volume: 1.0,
+            sink: "autoaudiosink".to_string(),
+        }
+    }
+}
+/// A sound file played once whenever a countdown's remaining time drops to
+/// `remaining` `unit`s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MilestoneSound {
+    pub remaining: i64,
+    pub unit: TimeUnit,
+    pub file: String,
+}
+impl Default for MilestoneSound {
+    fn default() -> Self {
+        Self {
+            remaining: 0,
+            unit: TimeUnit::Minute,
+            file: String::new(),
+        }
+    }
+}
+impl Threshold for MilestoneSound {
+    fn threshold(&self) -> (i64, TimeUnit) {
+        (self.remaining, self.unit)
+    }
+}
+/// Shows one countdown at a time, switching to the next every `page_seconds`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CarouselConfig {
+    pub enable: bool,
+    pub page_seconds: i32,
+    pub transition: Transition,
+    /// Length of the transition in milliseconds.
+    pub transition_duration: i32,
+}
+impl Default for CarouselConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            page_seconds: 10,
+            transition: Transition::Crossfade,
+            transition_duration: 500,
+        }
+    }
+}
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    /// Switch pages instantly.
+    Cut,
+    /// Fade the next page in over the current one.
+    #[default]
+    Crossfade,
+}
+impl FromStr for Transition {
+    type Err = String;
+
+    fn from_str(transition: &str) -> Result<Self, Self::Err> {
+        match transition {
+            "cut" => Ok(Transition::Cut),
+            "crossfade" => Ok(Transition::Crossfade),
+            _ => Err(format!("Invalid transition: '{}'", transition)),
+        }
+    }
+}
+/// One entry of `timers`. Keys left out fall back to the top-level key of the same name.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<TimeUnit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_mode: Option<DisplayMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breakdown_format: Option<String>,
+}
+/// A countdown as it is displayed, with the fallbacks of its `TimerConfig` applied.
+#[derive(Debug, Clone)]
+pub struct TimerSettings {
+    pub target: String,
+    pub direction: Direction,
+    pub header: String,
+    pub footer: String,
+    pub unit: TimeUnit,
+    pub precision: i32,
+    pub rounding: Rounding,
+    pub calendar_units: bool,
+    pub display_mode: DisplayMode,
+    pub breakdown_format: String,
+}
+impl Default for ConfigFile {
+    fn default() -> Self {
+        ConfigFile {
+            version: CONFIG_VERSION,
+            target: "2025-11-22 00:00:00".to_string(),
+            resume: false,
+            direction: Direction::Down,
+            interval: 100,
+            precision: 5,
+            header: "Header".to_string(),
+            footer: "Footer".to_string(),
+            header_fontsize: 50,
+            time_fontsize: 50,
+            footer_fontsize: 50,
+            window_title: "ChronoTimer".to_string(),
+            window_width: 200,
+            window_height: 250,
+            unit: TimeUnit::Day,
+            calendar_units: true,
+            display_mode: DisplayMode::Scalar,
+            breakdown_format: "{d}d {h:02}:{m:02}:{s:02}.{ms:03}".to_string(),
+            rounding: Rounding::Ceil,
+            refresh_mode: RefreshMode::Interval,
+            timers: Vec::new(),
+            on_finish: FinishConfig::default(),
+            hooks: Vec::new(),
+            notifications: Vec::new(),
+            sounds: SoundConfig::default(),
+            carousel_settings: CarouselConfig::default(),
+            clockmode_settings: ClockmodeConfigConfigfile::default(),
+            stopwatch_settings: StopwatchConfig::default(),
+        }
+    }
+}
+impl ConfigFile {
+    /// Parses a config file of any supported version, filling in missing keys with
+    /// their defaults. Every problem in the file is reported at once, each with the
+    /// position of its key.
+    pub fn from_yaml(content: &str) -> Result<Self, ConfigError> {
+        let mut value: Value = serde_yaml::from_str(content)?;
+        migrate(&mut value)?;
+
+        let mut problems = Vec::new();
+        // migrate() has made sure the root is a mapping.
+        let mapping = value.as_mapping_mut().unwrap();
+        if let Some(Value::Mapping(clockmode)) = mapping.get_mut("clockmode_settings") {
+            check_keys::<ClockmodeConfigConfigfile>(
+                clockmode,
+                "clockmode_settings.",
+                &mut problems,
+            );
+        }
+        if let Some(Value::Mapping(stopwatch)) = mapping.get_mut("stopwatch_settings") {
+            check_keys::<StopwatchConfig>(stopwatch, "stopwatch_settings.", &mut problems);
+        }
+        if let Some(Value::Mapping(on_finish)) = mapping.get_mut("on_finish") {
+            check_keys::<FinishConfig>(on_finish, "on_finish.", &mut problems);
+        }
+        if let Some(Value::Mapping(carousel)) = mapping.get_mut("carousel_settings") {
+            check_keys::<CarouselConfig>(carousel, "carousel_settings.", &mut problems);
+        }
+        if let Some(Value::Sequence(timers)) = mapping.get_mut("timers") {
+            for (i, timer) in timers.iter_mut().enumerate() {
+                if let Value::Mapping(timer) = timer {
+                    check_keys::<TimerConfig>(timer, &format!("timers.{}.", i), &mut problems);
+                }
+            }
+        }
+        if let Some(Value::Sequence(hooks)) = mapping.get_mut("hooks") {
+            for (i, hook) in hooks.iter_mut().enumerate() {
+                if let Value::Mapping(hook) = hook {
+                    check_keys::<HookConfig>(hook, &format!("hooks.{}.", i), &mut problems);
+                }
+            }
+        }
+        if let Some(Value::Sequence(notifications)) = mapping.get_mut("notifications") {
+            for (i, notification) in notifications.iter_mut().enumerate() {
+                if let Value::Mapping(notification) = notification {
+                    check_keys::<NotificationConfig>(
+                        notification,
+                        &format!("notifications.{}.", i),
+                        &mut problems,
+                    );
+                }
+            }
+        }
+        if let Some(Value::Mapping(sounds)) = mapping.get_mut("sounds") {
+            check_keys::<SoundConfig>(sounds, "sounds.", &mut problems);
+            if let Some(Value::Sequence(milestones)) = sounds.get_mut("milestones") {
+                for (i, milestone) in milestones.iter_mut().enumerate() {
+                    if let Value::Mapping(milestone) = milestone {
+                        check_keys::<MilestoneSound>(
+                            milestone,
+                            &format!("sounds.milestones.{}.", i),
+                            &mut problems,
+                        );
+                    }
+                }
+            }
+        }
+        check_keys::<ConfigFile>(mapping, "", &mut problems);
+        let config: ConfigFile = serde_yaml::from_value(value)?;
+        problems.extend(config.problems());
+
+        if problems.is_empty() {
+            return Ok(config);
+        }
+        for problem in &mut problems {
+            problem.position = diagnostic::locate(content, problem.key.as_str());
+        }
+        problems.sort_by_key(|p| p.position.unwrap_or((usize::MAX, 0)));
+        Err(ConfigError::Invalid(problems))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, ConfigError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_yaml(std::fs::read_to_string(path)?.as_str())
+    }
+
+    /// Loads `path`, falling back to the defaults if it cannot be read, and returns the
+    /// error reading it alongside. Unless `read_only` is set, the defaults are then
+    /// written to `path`, so there is a file to edit next time.
+    pub fn load_or_default(
+        path: impl AsRef<Path>,
+        read_only: bool,
+    ) -> Result<(Self, Option<std::io::Error>), ConfigError> {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(config) => Ok((config, None)),
+            Err(ConfigError::Io(e)) => {
+                let config = Self::default();
+                if !read_only {
+                    config.save(path)?;
+                }
+                Ok((config, Some(e)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(path, self.to_yaml()?)?)
+    }
+
+    /// The countdowns to display, in order. Always has at least one entry.
+    pub fn timer_settings(&self) -> Vec<TimerSettings> {
+        if self.timers.is_empty() {
+            return vec![self.resolve_timer(&TimerConfig::default())];
+        }
+        self.timers.iter().map(|t| self.resolve_timer(t)).collect()
+    }
+
+    fn resolve_timer(&self, timer: &TimerConfig) -> TimerSettings {
+        TimerSettings {
+            target: timer.target.clone().unwrap_or_else(|| self.target.clone()),
+            direction: timer.direction.unwrap_or(self.direction),
+            header: timer.header.clone().unwrap_or_else(|| self.header.clone()),
+            footer: timer.footer.clone().unwrap_or_else(|| self.footer.clone()),
+            unit: timer.unit.unwrap_or(self.unit),
+            precision: timer.precision.unwrap_or(self.precision),
+            rounding: self.rounding,
+            calendar_units: self.calendar_units,
+            display_mode: timer.display_mode.unwrap_or(self.display_mode),
+            breakdown_format: timer
+                .breakdown_format
+                .clone()
+                .unwrap_or_else(|| self.breakdown_format.clone()),
+        }
+    }
+
+    /// Checks the values serde cannot: anything the timer would choke on at runtime.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    fn problems(&self) -> Vec<Diagnostic> {
+        let mut problems = Vec::new();
+        if self.interval <= 0 {
+            problems.push(Diagnostic::new(
+                "interval",
+                format!("must be positive, got {}", self.interval),
+            ));
+        }
+        let timers = self.timer_settings();
+        check_precision(self.precision, "precision", &mut problems);
+        check_target(self.target.as_str(), "target", &mut problems);
+        // The top-level format only matters to timers in breakdown mode that inherit it.
+        let inherited_breakdown = if self.timers.is_empty() {
+            self.display_mode == DisplayMode::Breakdown
+        } else {
+            self.timers.iter().zip(&timers).any(|(t, s)| {
+                t.breakdown_format.is_none() && s.display_mode == DisplayMode::Breakdown
+            })
+        };
+        if inherited_breakdown {
+            check_breakdown(
+                self.breakdown_format.as_str(),
+                "breakdown_format",
+                &mut problems,
+            );
+        }
+        for (i, (timer, settings)) in self.timers.iter().zip(&timers).enumerate() {
+            if let Some(precision) = timer.precision {
+                check_precision(precision, &format!("timers.{}.precision", i), &mut problems);
+            }
+            if let Some(target) = &timer.target {
+                check_target(target, &format!("timers.{}.target", i), &mut problems);
+            }
+            if let Some(format) = &timer.breakdown_format
+                && settings.display_mode == DisplayMode::Breakdown
+            {
+                check_breakdown(
+                    format,
+                    &format!("timers.{}.breakdown_format", i),
+                    &mut problems,
+                );
+            }
+        }
+        for (i, hook) in self.hooks.iter().enumerate() {
+            if hook.remaining < 0 {
+                problems.push(Diagnostic::new(
+                    format!("hooks.{}.remaining", i),
+                    format!("must not be negative, got {}", hook.remaining),
+                ));
+            }
+            if hook.command.trim().is_empty() {
+                problems.push(Diagnostic::new(
+                    format!("hooks.{}.command", i),
+                    "must not be empty",
+                ));
+            }
+            if hook.timeout <= 0 {
+                problems.push(Diagnostic::new(
+                    format!("hooks.{}.timeout", i),
+                    format!("must be positive, got {}", hook.timeout),
+                ));
+            }
+        }
+        for (i, notification) in self.notifications.iter().enumerate() {
+            if notification.remaining < 0 {
+                problems.push(Diagnostic::new(
+                    format!("notifications.{}.remaining", i),
+                    format!("must not be negative, got {}", notification.remaining),
+                ));
+            }
+        }
+        let sounds = &self.sounds;
+        if !(0.0..=1.0).contains(&sounds.volume) {
+            problems.push(Diagnostic::new(
+                "sounds.volume",
+                format!("must be between 0 and 1, got {}", sounds.volume),
+            ));
+        }
+        if sounds.tick_seconds < 0 {
+            problems.push(Diagnostic::new(
+                "sounds.tick_seconds",
+                format!("must not be negative, got {}", sounds.tick_seconds),
+            ));
+        }
+        if sounds.sink.trim().is_empty() {
+            problems.push(Diagnostic::new("sounds.sink", "must not be empty"));
+        }
+        for (i, milestone) in sounds.milestones.iter().enumerate() {
+            if milestone.remaining < 0 {
+                problems.push(Diagnostic::new(
+                    format!("sounds.milestones.{}.remaining", i),
+                    format!("must not be negative, got {}", milestone.remaining),
+                ));
+            }
+            if milestone.file.trim().is_empty() {
+                problems.push(Diagnostic::new(
+                    format!("sounds.milestones.{}.file", i),
+                    "must not be empty",
+                ));
+            }
+        }
+        if self.stopwatch_settings.laps_shown < 0 {
+            problems.push(Diagnostic::new(
+                "stopwatch_settings.laps_shown",
+                format!(
+                    "must not be negative, got {}",
+                    self.stopwatch_settings.laps_shown
+                ),
+            ));
+        }
+        if self.carousel_settings.page_seconds <= 0 {
+            problems.push(Diagnostic::new(
+                "carousel_settings.page_seconds",
+                format!(
+                    "must be positive, got {}",
+                    self.carousel_settings.page_seconds
+                ),
+            ));
+        }
+        if self.carousel_settings.transition_duration < 0 {
+            problems.push(Diagnostic::new(
+                "carousel_settings.transition_duration",
+                format!(
+                    "must not be negative, got {}",
+                    self.carousel_settings.transition_duration
+                ),
+            ));
+        }
+        let fontsizes = [
+            ("header_fontsize", self.header_fontsize),
+            ("time_fontsize", self.time_fontsize),
+            ("footer_fontsize", self.footer_fontsize),
+            (
+                "clockmode_settings.fontsize",
+                self.clockmode_settings.fontsize,
+            ),
+        ];
+        for (key, size) in fontsizes {
+            if size <= 0 {
+                problems.push(Diagnostic::new(
+                    key,
+                    format!("must be positive, got {}", size),
+                ));
+            }
+        }
+        problems
+    }
+}
+
+fn check_precision(precision: i32, key: &str, problems: &mut Vec<Diagnostic>) {
+    if precision < 0 {
+        problems.push(Diagnostic::new(
+            key,
+            format!("must not be negative, got {}", precision),
+        ));
+    } else if precision as u32 > utils::MAX_PRECISION {
+        problems.push(Diagnostic::new(
+            key,
+            format!(
+                "must be at most {}, got {}",
+                utils::MAX_PRECISION,
+                precision
+            ),
+        ));
+    }
+}
+
+fn check_target(target: &str, key: &str, problems: &mut Vec<Diagnostic>) {
+    if let Err(e) = target.parse::<utils::Target>() {
+        problems.push(Diagnostic::new(key, e.to_string()));
+    }
+}
+
+fn check_breakdown(format: &str, key: &str, problems: &mut Vec<Diagnostic>) {
+    if let Err(e) = format.parse::<utils::Breakdown>() {
+        problems.push(Diagnostic::new(key, e.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for each test.
+    fn empty_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chronotimer-config-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn load_or_default_leaves_a_missing_file_alone_when_read_only() {
+        let path = empty_dir("read-only").join("config.yaml");
+        let (config, error) = ConfigFile::load_or_default(&path, true).unwrap();
+        assert_eq!(
+            config.to_yaml().unwrap(),
+            ConfigFile::default().to_yaml().unwrap()
+        );
+        assert_eq!(error.unwrap().kind(), std::io::ErrorKind::NotFound);
+        assert!(!path.exists());
+        assert!(!path.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn load_or_default_creates_a_missing_file() {
+        let path = empty_dir("create").join("config.yaml");
+        let (_, error) = ConfigFile::load_or_default(&path, false).unwrap();
+        assert!(error.is_some());
+        let created = ConfigFile::load(&path).unwrap();
+        assert_eq!(
+            created.to_yaml().unwrap(),
+            ConfigFile::default().to_yaml().unwrap()
+        );
+
+        let (_, error) = ConfigFile::load_or_default(&path, false).unwrap();
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn load_or_default_reports_invalid_files() {
+        let path = empty_dir("invalid").join("config.yaml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "precision: many\n").unwrap();
+        for read_only in [true, false] {
+            let result = ConfigFile::load_or_default(&path, read_only);
+            assert!(matches!(result, Err(ConfigError::Invalid(_))));
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "precision: many\n");
+    }
+
+    #[test]
+    fn from_yaml_reports_every_problem_in_file_order() {
+        let yaml = "\
+intervall: 100
+precision: -1
+clockmode_settings:
+  fontsize: big
+timers:
+- target: tomorrow 25:00
+  hedaer: x
+hooks:
+- command: ''
+";
+        let Err(ConfigError::Invalid(problems)) = ConfigFile::from_yaml(yaml) else {
+            panic!("the config should be invalid");
+        };
+        let found: Vec<(&str, Option<(usize, usize)>)> = problems
+            .iter()
+            .map(|p| (p.key.as_str(), p.position))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("intervall", Some((1, 1))),
+                ("precision", Some((2, 1))),
+                ("clockmode_settings.fontsize", Some((4, 3))),
+                ("timers.0.target", Some((6, 3))),
+                ("timers.0.hedaer", Some((7, 3))),
+                ("hooks.0.command", Some((9, 3))),
+            ]
+        );
+        assert!(problems[0].message.starts_with("unknown field `intervall`"));
+    }
+}
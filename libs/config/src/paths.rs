@@ -0,0 +1,47 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::env;
+use std::path::PathBuf;
+
+pub const CONFIG_FILE_NAME: &str = "config.yaml";
+
+/// `$XDG_CONFIG_HOME/chronotimer/config.yaml`, falling back to `~/.config` when the
+/// variable is unset and to `%APPDATA%` on systems without a home directory.
+pub fn user_config_file() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("chronotimer").join(CONFIG_FILE_NAME))
+}
+
+/// `config.yaml` next to the running binary, for portable installs.
+pub fn portable_config_file() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    Some(exe.parent()?.join(CONFIG_FILE_NAME))
+}
+
+/// Picks the config file to use: `explicit` (from `--config`), then
+/// `$CHRONOTIMER_CONFIG`, then the user config file if it exists, then a portable
+/// file if it exists. With none of those present, the user config file is where a
+/// fresh default config belongs.
+pub fn find_config_file(explicit: Option<PathBuf>) -> PathBuf {
+    if let Some(path) = explicit {
+        return path;
+    }
+    if let Some(path) = env::var_os("CHRONOTIMER_CONFIG").filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    let user = user_config_file();
+    if let Some(path) = user.as_ref().filter(|p| p.exists()) {
+        return path.clone();
+    }
+    let portable = portable_config_file();
+    if let Some(path) = portable.as_ref().filter(|p| p.exists()) {
+        return path.clone();
+    }
+    user.or(portable)
+        .unwrap_or_else(|| PathBuf::from(CONFIG_FILE_NAME))
+}
@@ -0,0 +1,309 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SoundError {
+    #[error("Failed to initialize GStreamer: {0}")]
+    Init(glib::Error),
+    #[error("Invalid sound file '{file}': {message}")]
+    File { file: String, message: String },
+    #[error("Invalid audio sink '{sink}': {source}")]
+    Sink { sink: String, source: glib::Error },
+    #[error("Failed to create playbin: {0}")]
+    Playbin(glib::BoolError),
+    #[error("Failed to play '{uri}': {source}")]
+    Start {
+        uri: String,
+        source: gst::StateChangeError,
+    },
+}
+
+/// Turns a sound file from the config into a URI for `playbin`. URIs are kept as they
+/// are, and relative paths are taken from `dir`, the directory of the config file.
+pub fn resolve_uri(file: &str, dir: &Path) -> Result<String, SoundError> {
+    if file.contains("://") {
+        return Ok(file.to_string());
+    }
+    let invalid = |message: String| SoundError::File {
+        file: file.to_string(),
+        message,
+    };
+    let path = std::path::absolute(dir.join(file)).map_err(|e| invalid(e.to_string()))?;
+    glib::filename_to_uri(path, None)
+        .map(|uri| uri.to_string())
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// Plays sound files through GStreamer's `playbin`, driven by the GLib main loop of
+/// the calling thread so the display never waits for it. Each sound gets its own
+/// `playbin`, so sounds may overlap, and is torn down once it has played. Dropping the
+/// player stops every sound it is still playing.
+pub struct Player {
+    sink: String,
+    volume: f64,
+    playing: Rc<RefCell<Vec<Playback>>>,
+    next_id: Cell<u64>,
+}
+
+struct Playback {
+    id: u64,
+    playbin: gst::Element,
+    _watch: gst::bus::BusWatchGuard,
+}
+impl Drop for Playback {
+    fn drop(&mut self) {
+        let _ = self.playbin.set_state(gst::State::Null);
+    }
+}
+
+impl Player {
+    /// A player for `sink`, a GStreamer sink description such as `autoaudiosink` or
+    /// `fakesink`, at `volume` from 0.0 (silent) to 1.0 (full volume).
+    pub fn new(sink: &str, volume: f64) -> Result<Self, SoundError> {
+        gst::init().map_err(SoundError::Init)?;
+        Ok(Self {
+            sink: sink.to_string(),
+            volume,
+            playing: Rc::new(RefCell::new(Vec::new())),
+            next_id: Cell::new(0),
+        })
+    }
+
+    /// Starts playing `uri`. Errors while it plays are logged.
+    pub fn play(&self, uri: &str) -> Result<(), SoundError> {
+        let logged = uri.to_string();
+        self.play_then(uri, move |result| {
+            if let Err(e) = result {
+                eprintln!("Failed to play '{}': {}", logged, e);
+            }
+        })
+    }
+
+    /// Starts playing `uri` and calls `done` from the main loop once it has played to
+    /// the end or failed.
+    pub fn play_then(
+        &self,
+        uri: &str,
+        done: impl FnOnce(Result<(), glib::Error>) + 'static,
+    ) -> Result<(), SoundError> {
+        let sink =
+            gst::parse::bin_from_description(self.sink.as_str(), true).map_err(|source| {
+                SoundError::Sink {
+                    sink: self.sink.clone(),
+                    source,
+                }
+            })?;
+        let playbin = gst::ElementFactory::make("playbin")
+            .property("uri", uri)
+            .property("volume", self.volume)
+            .property("audio-sink", sink.upcast_ref::<gst::Element>())
+            .build()
+            .map_err(SoundError::Playbin)?;
+        if let Err(source) = playbin.set_state(gst::State::Playing) {
+            let _ = playbin.set_state(gst::State::Null);
+            return Err(SoundError::Start {
+                uri: uri.to_string(),
+                source,
+            });
+        }
+
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let bus = playbin.bus().expect("a pipeline always has a bus");
+        let watch = bus
+            .add_watch_local({
+                let playing = Rc::downgrade(&self.playing);
+                let mut done = Some(done);
+                move |_, message| {
+                    let result = match message.view() {
+                        gst::MessageView::Eos(_) => Ok(()),
+                        gst::MessageView::Error(error) => Err(error.error()),
+                        _ => return glib::ControlFlow::Continue,
+                    };
+                    // The main loop keeps this closure alive until it returns, even
+                    // though removing the playback removes the watch.
+                    finish(&playing, id);
+                    if let Some(done) = done.take() {
+                        done(result);
+                    }
+                    glib::ControlFlow::Break
+                }
+            })
+            .map_err(SoundError::Playbin)?;
+        self.playing.borrow_mut().push(Playback {
+            id,
+            playbin,
+            _watch: watch,
+        });
+        Ok(())
+    }
+}
+
+/// Stops and drops the playback `id`, if the player is still around.
+fn finish(playing: &Weak<RefCell<Vec<Playback>>>, id: u64) {
+    let Some(playing) = playing.upgrade() else {
+        return;
+    };
+    let playback = {
+        let mut playing = playing.borrow_mut();
+        let index = playing.iter().position(|p| p.id == id);
+        index.map(|index| playing.remove(index))
+    };
+    drop(playback);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn resolve_uri_keeps_uris() {
+        let dir = Path::new("/etc/chronotimer");
+        for uri in ["file:///tmp/bell.ogg", "https://example.com/bell.ogg"] {
+            assert_eq!(resolve_uri(uri, dir).unwrap(), uri);
+        }
+    }
+
+    #[test]
+    fn resolve_uri_takes_relative_paths_from_dir() {
+        let dir = Path::new("/etc/chronotimer");
+        let cases = [
+            ("bell.ogg", "file:///etc/chronotimer/bell.ogg"),
+            ("sounds/bell.ogg", "file:///etc/chronotimer/sounds/bell.ogg"),
+            ("../bell.ogg", "file:///etc/chronotimer/../bell.ogg"),
+            ("/usr/share/bell.ogg", "file:///usr/share/bell.ogg"),
+            ("new year.ogg", "file:///etc/chronotimer/new%20year.ogg"),
+        ];
+        for (file, uri) in cases {
+            assert_eq!(resolve_uri(file, dir).unwrap(), uri, "{file}");
+        }
+    }
+
+    #[test]
+    fn resolve_uri_makes_relative_dirs_absolute() {
+        let uri = resolve_uri("bell.ogg", Path::new("")).unwrap();
+        let expected = std::env::current_dir().unwrap().join("bell.ogg");
+        assert_eq!(uri, glib::filename_to_uri(expected, None).unwrap());
+    }
+
+    #[test]
+    fn unknown_sinks_are_rejected() {
+        let player = Player::new("nosuchsink", 1.0).unwrap();
+        let result = player.play_then("file:///tmp/bell.wav", |_| {});
+        assert!(
+            matches!(result, Err(SoundError::Sink { ref sink, .. }) if sink == "nosuchsink"),
+            "{result:?}"
+        );
+    }
+
+    /// One second of silence as 8 kHz, 8-bit mono PCM.
+    fn write_wav(path: &Path) {
+        let samples = vec![128_u8; 8000];
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((36 + samples.len() as u32).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16_u32.to_le_bytes());
+        wav.extend(1_u16.to_le_bytes());
+        wav.extend(1_u16.to_le_bytes());
+        wav.extend(8000_u32.to_le_bytes());
+        wav.extend(8000_u32.to_le_bytes());
+        wav.extend(1_u16.to_le_bytes());
+        wav.extend(8_u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend((samples.len() as u32).to_le_bytes());
+        wav.extend(samples);
+        std::fs::write(path, wav).unwrap();
+    }
+
+    /// Plays `file` from a fresh directory through `fakesink` on this thread's own
+    /// main loop and returns how it ended, or `None` if it did not end in time.
+    fn play_to_end(file: &str, write: bool) -> Result<Option<Result<(), glib::Error>>, SoundError> {
+        let dir = std::env::temp_dir().join(format!("chronotimer-sound-{}", file));
+        std::fs::create_dir_all(&dir).unwrap();
+        if write {
+            write_wav(&dir.join(file));
+        }
+        let uri = resolve_uri(file, &dir).unwrap();
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let main_loop = glib::MainLoop::new(Some(&context), false);
+                let ended = Rc::new(RefCell::new(None));
+                let player = Player::new("fakesink sync=true", 0.5).unwrap();
+                player.play_then(uri.as_str(), {
+                    let ended = ended.clone();
+                    let main_loop = main_loop.clone();
+                    move |result| {
+                        ended.replace(Some(result));
+                        main_loop.quit();
+                    }
+                })?;
+                let timeout = glib::timeout_source_new(
+                    Duration::from_secs(10),
+                    None,
+                    glib::Priority::DEFAULT,
+                    {
+                        let main_loop = main_loop.clone();
+                        move || {
+                            main_loop.quit();
+                            glib::ControlFlow::Break
+                        }
+                    },
+                );
+                timeout.attach(Some(&context));
+                main_loop.run();
+                timeout.destroy();
+                assert!(player.playing.borrow().is_empty());
+                Ok(ended.take())
+            })
+            .unwrap()
+    }
+
+    fn can_play_wav() -> bool {
+        gst::init().unwrap();
+        let missing: Vec<&str> = ["playbin", "wavparse", "audioconvert", "fakesink"]
+            .into_iter()
+            .filter(|name| gst::ElementFactory::find(name).is_none())
+            .collect();
+        if !missing.is_empty() {
+            eprintln!(
+                "Skipped, missing GStreamer elements: {}",
+                missing.join(", ")
+            );
+        }
+        missing.is_empty()
+    }
+
+    #[test]
+    fn plays_to_the_end_through_fakesink() {
+        if !can_play_wav() {
+            return;
+        }
+        let ended = play_to_end("silence.wav", true);
+        assert!(matches!(ended, Ok(Some(Ok(())))), "{ended:?}");
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        if !can_play_wav() {
+            return;
+        }
+        // Depending on the GStreamer version, opening the file fails right away or
+        // while playing.
+        let ended = play_to_end("missing.wav", false);
+        assert!(
+            matches!(ended, Err(SoundError::Start { .. }) | Ok(Some(Err(_)))),
+            "{ended:?}"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{Rounding, TimeUnit, UnitSpan, add_calendar_months, whole_calendar_units};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InvalidBreakdownError {
+    #[error("Invalid breakdown format: unknown unit '{0}'")]
+    UnknownUnit(String),
+    #[error("Invalid breakdown format: invalid width '{0}'")]
+    InvalidWidth(String),
+    #[error("Invalid breakdown format: unclosed '{{'")]
+    Unclosed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field { unit: TimeUnit, width: usize },
+}
+
+/// A mixed-unit display template such as `{d}d {h:02}:{m:02}:{s:02}.{ms:03}`.
+///
+/// Each `{unit}` or `{unit:0N}` placeholder takes whatever is left after the larger
+/// units in the template, so the largest unit absorbs any overflow. `{{` and `}}` are
+/// literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakdown {
+    parts: Vec<Part>,
+}
+impl FromStr for Breakdown {
+    type Err = InvalidBreakdownError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(InvalidBreakdownError::Unclosed),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_field(field.trim())?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+}
+
+fn parse_field(field: &str) -> Result<Part, InvalidBreakdownError> {
+    let (name, width) = match field.split_once(':') {
+        Some((name, width)) => {
+            let width = width
+                .parse()
+                .map_err(|_| InvalidBreakdownError::InvalidWidth(width.to_string()))?;
+            (name, width)
+        }
+        None => (field, 0),
+    };
+    let unit = name
+        .parse()
+        .map_err(|_| InvalidBreakdownError::UnknownUnit(name.to_string()))?;
+    Ok(Part::Field { unit, width })
+}
+
+/// Breaks the span from `from` to `to` into the units of `breakdown`.
+///
+/// The span is first rounded to a whole number of the smallest unit in the template,
+/// so `{m}:{s:02}` under `ceil` shows `0:01` until the target is reached. With
+/// `calendar` set, years and months are counted on the real calendar starting at
+/// `from`; otherwise they are 365 and 30 days long. Spans that have already passed are
+/// shown with a leading `-`.
+pub fn format_breakdown(
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+    breakdown: &Breakdown,
+    calendar: bool,
+    rounding: Rounding,
+) -> String {
+    // Rounding applies to the signed span, so a passed span rounds the other way.
+    let (sign, from, to, rounding) = if to < from {
+        ("-", to, from, rounding.mirrored())
+    } else {
+        ("", from, to, rounding)
+    };
+    let from = from.with_timezone(&to.timezone());
+    let to = round_end(from, to, breakdown, calendar, rounding);
+    let sign = if to == from { "" } else { sign };
+
+    let mut values = [0_i64; TimeUnit::ALL.len()];
+    let mut cursor = from;
+    let mut rest = (to - from).num_milliseconds();
+    for unit in TimeUnit::ALL.into_iter().rev() {
+        let used = breakdown
+            .parts
+            .iter()
+            .any(|p| matches!(p, Part::Field { unit: u, .. } if *u == unit));
+        if !used {
+            continue;
+        }
+        match unit.calendar_months() {
+            Some(months_per_unit) if calendar => {
+                let (whole, end) = whole_calendar_units(cursor, to, months_per_unit);
+                values[unit as usize] = whole;
+                cursor = end;
+                rest = (to - cursor).num_milliseconds();
+            }
+            _ => {
+                values[unit as usize] = rest / unit.millis();
+                rest %= unit.millis();
+                cursor = to - TimeDelta::milliseconds(rest);
+            }
+        }
+    }
+
+    let mut out = String::from(sign);
+    for part in &breakdown.parts {
+        match part {
+            Part::Literal(text) => out.push_str(text),
+            Part::Field { unit, width } => out.push_str(&format!(
+                "{:0width$}",
+                values[*unit as usize],
+                width = *width
+            )),
+        }
+    }
+    out
+}
+
+/// Moves `to` (`from <= to`) so that the span is a whole number of the smallest unit
+/// in `breakdown`, rounded with `rounding`.
+fn round_end(
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+    breakdown: &Breakdown,
+    calendar: bool,
+    rounding: Rounding,
+) -> DateTime<FixedOffset> {
+    let smallest = breakdown.parts.iter().filter_map(|part| match part {
+        Part::Field { unit, .. } => Some(*unit),
+        Part::Literal(_) => None,
+    });
+    let Some(unit) = smallest.min_by_key(|unit| unit.millis()) else {
+        return to;
+    };
+    let count = UnitSpan::new(from, to, unit, calendar).scaled(0, rounding) as i64;
+    match unit.calendar_months() {
+        Some(months_per_unit) if calendar => {
+            add_calendar_months(from, count * months_per_unit).unwrap_or(to)
+        }
+        _ => from
+            .checked_add_signed(TimeDelta::milliseconds(count * unit.millis()))
+            .unwrap_or(to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    fn format(from: &str, to: &str, template: &str, rounding: Rounding) -> String {
+        let breakdown = template.parse().unwrap();
+        format_breakdown(at(from), at(to), &breakdown, true, rounding)
+    }
+
+    #[test]
+    fn rounds_the_smallest_field() {
+        let now = "2026-12-31T23:59:59.500+08:00";
+        let target = "2027-01-01T00:00:00+08:00";
+        let hms = "{h}:{m:02}:{s:02}";
+        let cases = [
+            (Rounding::Ceil, "0:00:01"),
+            (Rounding::Floor, "0:00:00"),
+            (Rounding::Nearest, "0:00:01"),
+        ];
+        for (rounding, expected) in cases {
+            assert_eq!(format(now, target, hms, rounding), expected, "{rounding}");
+        }
+        assert_eq!(format(now, target, "{s}.{ms:03}", Rounding::Floor), "0.500");
+    }
+
+    #[test]
+    fn rounding_carries_into_larger_fields() {
+        let from = "2026-01-01T00:00:00Z";
+        assert_eq!(
+            format(
+                from,
+                "2026-01-01T00:59:59.001Z",
+                "{h}:{m:02}:{s:02}",
+                Rounding::Ceil
+            ),
+            "1:00:00"
+        );
+        assert_eq!(
+            format(from, "2026-12-15T00:00:00Z", "{y}y {mo}mo", Rounding::Ceil),
+            "1y 0mo"
+        );
+        assert_eq!(
+            format(from, "2026-12-15T00:00:00Z", "{y}y {mo}mo", Rounding::Floor),
+            "0y 11mo"
+        );
+        assert_eq!(
+            format(from, "2026-03-01T00:00:00Z", "{mo}mo {d}d", Rounding::Ceil),
+            "2mo 0d"
+        );
+    }
+
+    #[test]
+    fn passed_spans_round_towards_zero_under_ceil() {
+        let target = "2026-01-01T00:00:00Z";
+        let now = "2026-01-01T00:00:00.500Z";
+        let hms = "{h}:{m:02}:{s:02}";
+        assert_eq!(format(now, target, hms, Rounding::Ceil), "0:00:00");
+        assert_eq!(format(now, target, hms, Rounding::Floor), "-0:00:01");
+        assert_eq!(format(now, target, hms, Rounding::Nearest), "-0:00:01");
+        assert_eq!(
+            format("2026-01-01T01:02:03.400Z", target, hms, Rounding::Ceil),
+            "-1:02:03"
+        );
+    }
+
+    #[test]
+    fn largest_field_absorbs_overflow() {
+        assert_eq!(
+            format(
+                "2026-01-01T00:00:00Z",
+                "2026-01-03T01:00:00Z",
+                "{h}h {m}m",
+                Rounding::Ceil
+            ),
+            "49h 0m"
+        );
+        assert_eq!(
+            format(
+                "2026-01-01T00:00:00Z",
+                "2026-01-01T00:00:00Z",
+                "{{{s}}}",
+                Rounding::Ceil
+            ),
+            "{0}"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::rc::Rc;
+
+/// Source of the current time. Everything that displays time asks a `Clock` rather
+/// than the system, so the display can be previewed at any instant or speed.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Seconds of this clock that pass per real second.
+    fn rate(&self) -> f64 {
+        1.0
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Rc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn rate(&self) -> f64 {
+        (**self).rate()
+    }
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that always reads the same instant.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+
+    fn rate(&self) -> f64 {
+        0.0
+    }
+}
+
+/// Another clock shifted by a constant offset.
+#[derive(Debug, Clone)]
+pub struct OffsetClock<C> {
+    inner: C,
+    offset: TimeDelta,
+}
+impl<C: Clock> OffsetClock<C> {
+    pub fn new(inner: C, offset: TimeDelta) -> Self {
+        Self { inner, offset }
+    }
+
+    /// Shifts `inner` so that it reads `start` right now.
+    pub fn starting_at(inner: C, start: DateTime<Utc>) -> Self {
+        let offset = start - inner.now();
+        Self { inner, offset }
+    }
+}
+impl<C: Clock> Clock for OffsetClock<C> {
+    fn now(&self) -> DateTime<Utc> {
+        self.inner.now() + self.offset
+    }
+
+    fn rate(&self) -> f64 {
+        self.inner.rate()
+    }
+}
+
+/// Another clock running `speed` times as fast, starting from the moment it was created.
+#[derive(Debug, Clone)]
+pub struct ScaledClock<C> {
+    inner: C,
+    origin: DateTime<Utc>,
+    speed: f64,
+}
+impl<C: Clock> ScaledClock<C> {
+    pub fn new(inner: C, speed: f64) -> Self {
+        let origin = inner.now();
+        Self {
+            inner,
+            origin,
+            speed,
+        }
+    }
+}
+impl<C: Clock> Clock for ScaledClock<C> {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = (self.inner.now() - self.origin).num_microseconds();
+        let scaled = elapsed.map_or(0.0, |us| us as f64 * self.speed);
+        self.origin + TimeDelta::microseconds(scaled as i64)
+    }
+
+    fn rate(&self) -> f64 {
+        self.inner.rate() * self.speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{ManualClock, at};
+
+    #[test]
+    fn fixed_clock_stands_still() {
+        let clock = FixedClock(at(1_798_732_800));
+        assert_eq!(clock.now(), at(1_798_732_800));
+        assert_eq!(clock.now(), clock.now());
+        assert_eq!(clock.rate(), 0.0);
+    }
+
+    #[test]
+    fn offset_clock_shifts_its_inner_clock() {
+        let clock = OffsetClock::new(FixedClock(at(1000)), TimeDelta::seconds(-300));
+        assert_eq!(clock.now(), at(700));
+        assert_eq!(clock.rate(), 0.0);
+
+        let inner = Rc::new(ManualClock::new(at(1000)));
+        let clock = OffsetClock::starting_at(inner.clone(), at(5000));
+        assert_eq!(clock.now(), at(5000));
+        inner.advance(TimeDelta::seconds(10));
+        assert_eq!(clock.now(), at(5010));
+        assert_eq!(clock.rate(), 1.0);
+    }
+
+    #[test]
+    fn scaled_clock_runs_faster_from_its_creation() {
+        let inner = Rc::new(ManualClock::new(at(1000)));
+        let clock = ScaledClock::new(inner.clone(), 60.0);
+        assert_eq!(clock.now(), at(1000));
+        inner.advance(TimeDelta::seconds(2));
+        assert_eq!(clock.now(), at(1120));
+        inner.advance(TimeDelta::milliseconds(500));
+        assert_eq!(clock.now(), at(1150));
+        assert_eq!(clock.rate(), 60.0);
+
+        let slow = ScaledClock::new(inner.clone(), 0.5);
+        inner.advance(TimeDelta::seconds(4));
+        assert_eq!(slow.now(), at(1004) + TimeDelta::milliseconds(500));
+    }
+
+    #[test]
+    fn wrapped_clocks_compose() {
+        // Preview a target a day ahead at ten times the speed.
+        let inner = Rc::new(ManualClock::new(at(0)));
+        let clock: Rc<dyn Clock> = Rc::new(ScaledClock::new(
+            OffsetClock::starting_at(inner.clone(), at(86_400)),
+            10.0,
+        ));
+        inner.advance(TimeDelta::seconds(3));
+        assert_eq!(clock.now(), at(86_430));
+        assert_eq!(clock.rate(), 10.0);
+
+        let frozen = ScaledClock::new(FixedClock(at(42)), 10.0);
+        assert_eq!(frozen.now(), at(42));
+        assert_eq!(frozen.rate(), 0.0);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::Clock;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Pause, resume and reset for a timer, as a state machine over the readings of a
+/// clock. The timer's own time advances with the clock while running and stands
+/// still while paused, so it trails the clock by exactly the total paused time.
+#[derive(Debug, Clone)]
+pub struct TimerControl {
+    /// When the timer was started or last reset.
+    origin: DateTime<Utc>,
+    /// The length of every finished pause since `origin`.
+    paused: TimeDelta,
+    /// When the current pause began.
+    paused_at: Option<DateTime<Utc>>,
+}
+impl TimerControl {
+    /// A running timer started at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            origin: now,
+            paused: TimeDelta::zero(),
+            paused_at: None,
+        }
+    }
+
+    pub fn state(&self) -> TimerState {
+        match self.paused_at {
+            Some(_) => TimerState::Paused,
+            None => TimerState::Running,
+        }
+    }
+
+    /// When the timer was started or last reset.
+    pub fn origin(&self) -> DateTime<Utc> {
+        self.origin
+    }
+
+    /// Pauses at `now`. Does nothing if already paused.
+    pub fn pause(&mut self, now: DateTime<Utc>) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Resumes at `now`. Does nothing if running.
+    pub fn resume(&mut self, now: DateTime<Utc>) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused += (now - paused_at).max(TimeDelta::zero());
+        }
+    }
+
+    /// Pauses if running and resumes if paused, returning the new state.
+    pub fn toggle(&mut self, now: DateTime<Utc>) -> TimerState {
+        match self.state() {
+            TimerState::Running => self.pause(now),
+            TimerState::Paused => self.resume(now),
+        }
+        self.state()
+    }
+
+    /// Starts over at `now` with no time elapsed, keeping the timer paused if it was.
+    pub fn reset(&mut self, now: DateTime<Utc>) {
+        let state = self.state();
+        *self = Self::new(now);
+        if state == TimerState::Paused {
+            self.pause(now);
+        }
+    }
+
+    /// The running time between `origin` and `now`, leaving out every pause.
+    pub fn elapsed(&self, now: DateTime<Utc>) -> TimeDelta {
+        let end = self.paused_at.unwrap_or(now);
+        end - self.origin - self.paused
+    }
+
+    /// The timer's own time at `now`.
+    pub fn time(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.origin + self.elapsed(now)
+    }
+}
+
+/// Another clock that can be paused and reset through a `TimerControl`. It reads the
+/// timer's own time, and stands still while paused.
+pub struct PausableClock<C> {
+    inner: C,
+    control: RefCell<TimerControl>,
+}
+impl<C: Clock> PausableClock<C> {
+    pub fn new(inner: C) -> Self {
+        let control = RefCell::new(TimerControl::new(inner.now()));
+        Self { inner, control }
+    }
+
+    /// The clock this one reads, which keeps running while paused.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn state(&self) -> TimerState {
+        self.control.borrow().state()
+    }
+
+    /// This clock's time when the inner clock reads `now`.
+    pub fn time_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.control.borrow().time(now)
+    }
+
+    /// When the clock was created or last reset, on the inner clock.
+    pub fn origin(&self) -> DateTime<Utc> {
+        self.control.borrow().origin()
+    }
+
+    /// How far this clock is behind the inner clock, which is the total time paused.
+    pub fn lag(&self) -> TimeDelta {
+        let now = self.inner.now();
+        now - self.time_at(now)
+    }
+
+    /// Pauses now. Does nothing if already paused.
+    pub fn pause(&self) {
+        self.control.borrow_mut().pause(self.inner.now());
+    }
+
+    pub fn toggle(&self) -> TimerState {
+        self.control.borrow_mut().toggle(self.inner.now())
+    }
+
+    pub fn reset(&self) {
+        self.control.borrow_mut().reset(self.inner.now());
+    }
+}
+impl<C: Clock> Clock for PausableClock<C> {
+    fn now(&self) -> DateTime<Utc> {
+        self.time_at(self.inner.now())
+    }
+
+    fn rate(&self) -> f64 {
+        match self.state() {
+            TimerState::Running => self.inner.rate(),
+            TimerState::Paused => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{ManualClock, at};
+    use std::rc::Rc;
+
+    #[test]
+    fn pause_and_resume_leave_out_the_pause() {
+        let mut control = TimerControl::new(at(1000));
+        assert_eq!(control.state(), TimerState::Running);
+        assert_eq!(control.elapsed(at(1010)), TimeDelta::seconds(10));
+
+        control.pause(at(1010));
+        assert_eq!(control.state(), TimerState::Paused);
+        assert_eq!(control.elapsed(at(1030)), TimeDelta::seconds(10));
+        assert_eq!(control.time(at(1030)), at(1010));
+
+        control.resume(at(1030));
+        assert_eq!(control.state(), TimerState::Running);
+        assert_eq!(control.elapsed(at(1035)), TimeDelta::seconds(15));
+        assert_eq!(control.time(at(1035)), at(1015));
+        assert_eq!(control.origin(), at(1000));
+    }
+
+    #[test]
+    fn repeated_pauses_add_up() {
+        let mut control = TimerControl::new(at(0));
+        assert_eq!(control.toggle(at(10)), TimerState::Paused);
+        // Pausing again keeps the first pause, resuming again changes nothing.
+        control.pause(at(15));
+        assert_eq!(control.toggle(at(20)), TimerState::Running);
+        control.resume(at(25));
+        assert_eq!(control.toggle(at(30)), TimerState::Paused);
+        assert_eq!(control.toggle(at(35)), TimerState::Running);
+        assert_eq!(control.elapsed(at(40)), TimeDelta::seconds(25));
+    }
+
+    #[test]
+    fn resuming_before_the_pause_adds_no_time() {
+        let mut control = TimerControl::new(at(0));
+        control.pause(at(10));
+        control.resume(at(5));
+        assert_eq!(control.elapsed(at(20)), TimeDelta::seconds(20));
+    }
+
+    #[test]
+    fn reset_starts_over_in_the_same_state() {
+        let mut control = TimerControl::new(at(0));
+        control.pause(at(10));
+        control.resume(at(20));
+        control.reset(at(30));
+        assert_eq!(control.state(), TimerState::Running);
+        assert_eq!(control.origin(), at(30));
+        assert_eq!(control.elapsed(at(35)), TimeDelta::seconds(5));
+
+        control.pause(at(40));
+        control.reset(at(50));
+        assert_eq!(control.state(), TimerState::Paused);
+        assert_eq!(control.origin(), at(50));
+        assert_eq!(control.elapsed(at(60)), TimeDelta::zero());
+        control.resume(at(60));
+        assert_eq!(control.elapsed(at(70)), TimeDelta::seconds(10));
+    }
+
+    #[test]
+    fn pausable_clock_stands_still_while_paused() {
+        let inner = Rc::new(ManualClock::new(at(1000)));
+        let clock = PausableClock::new(inner.clone());
+        assert_eq!(clock.origin(), at(1000));
+        inner.advance(TimeDelta::seconds(10));
+        assert_eq!(clock.now(), at(1010));
+        assert_eq!(clock.rate(), 1.0);
+        assert_eq!(clock.lag(), TimeDelta::zero());
+
+        assert_eq!(clock.toggle(), TimerState::Paused);
+        inner.advance(TimeDelta::seconds(20));
+        assert_eq!(clock.now(), at(1010));
+        assert_eq!(clock.rate(), 0.0);
+        assert_eq!(clock.lag(), TimeDelta::seconds(20));
+        assert_eq!(clock.inner().now(), at(1030));
+
+        assert_eq!(clock.toggle(), TimerState::Running);
+        inner.advance(TimeDelta::seconds(5));
+        assert_eq!(clock.now(), at(1015));
+        assert_eq!(clock.time_at(at(1040)), at(1020));
+        assert_eq!(clock.lag(), TimeDelta::seconds(20));
+
+        clock.pause();
+        clock.pause();
+        inner.advance(TimeDelta::seconds(5));
+        assert_eq!(clock.state(), TimerState::Paused);
+        assert_eq!(clock.now(), at(1015));
+    }
+
+    #[test]
+    fn pausable_clock_reset_drops_the_lag() {
+        let inner = Rc::new(ManualClock::new(at(1000)));
+        let clock = PausableClock::new(inner.clone());
+        clock.toggle();
+        inner.advance(TimeDelta::seconds(30));
+        clock.reset();
+        assert_eq!(clock.state(), TimerState::Paused);
+        assert_eq!(clock.origin(), at(1030));
+        assert_eq!(clock.lag(), TimeDelta::zero());
+        inner.advance(TimeDelta::seconds(10));
+        assert_eq!(clock.lag(), TimeDelta::seconds(10));
+        clock.toggle();
+        clock.reset();
+        assert_eq!(clock.now(), at(1040));
+        assert_eq!(clock.lag(), TimeDelta::zero());
+    }
+}
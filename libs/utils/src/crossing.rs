@@ -0,0 +1,123 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::TimeDelta;
+
+/// Reports when the remaining time of a countdown drops to a threshold, once per
+/// crossing. The first observation only records which side of the threshold the time
+/// is on, so a threshold that had already passed at startup or after a reload does
+/// not fire. If the remaining time rises above the threshold again, it fires again on
+/// the next crossing.
+#[derive(Debug, Clone)]
+pub struct Crossing {
+    threshold: TimeDelta,
+    reached: Option<bool>,
+}
+impl Crossing {
+    pub fn new(threshold: TimeDelta) -> Self {
+        Self {
+            threshold,
+            reached: None,
+        }
+    }
+
+    /// Records `remaining` and returns whether it has just reached the threshold.
+    pub fn update(&mut self, remaining: TimeDelta) -> bool {
+        let reached = remaining <= self.threshold;
+        let crossed = self.reached == Some(false) && reached;
+        self.reached = Some(reached);
+        crossed
+    }
+}
+
+/// Reports each whole second a countdown passes during its last `seconds` seconds,
+/// counting 3, 2, 1 as the remaining time drops to those values. Like `Crossing`, the
+/// first observation only records the current second.
+#[derive(Debug, Clone)]
+pub struct LastSeconds {
+    seconds: i64,
+    last: Option<i64>,
+}
+impl LastSeconds {
+    pub fn new(seconds: i64) -> Self {
+        Self {
+            seconds,
+            last: None,
+        }
+    }
+
+    /// Records `remaining` and returns whether a second inside the window has just
+    /// been reached.
+    pub fn update(&mut self, remaining: TimeDelta) -> bool {
+        let millis = remaining.num_milliseconds();
+        // The whole seconds still to go, so exactly 3s remaining counts as 3.
+        let second = millis.div_euclid(1000) + (millis.rem_euclid(1000) != 0) as i64;
+        let ticked =
+            self.last.is_some_and(|last| second < last) && (1..=self.seconds).contains(&second);
+        self.last = Some(second);
+        ticked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds remaining times in seconds and returns the ones that fired.
+    fn fired(mut update: impl FnMut(TimeDelta) -> bool, remaining: &[f64]) -> Vec<f64> {
+        remaining
+            .iter()
+            .copied()
+            .filter(|&seconds| update(TimeDelta::milliseconds((seconds * 1000.0) as i64)))
+            .collect()
+    }
+
+    fn crossing(threshold: i64, remaining: &[f64]) -> Vec<f64> {
+        let mut crossing = Crossing::new(TimeDelta::seconds(threshold));
+        fired(|r| crossing.update(r), remaining)
+    }
+
+    fn last_seconds(seconds: i64, remaining: &[f64]) -> Vec<f64> {
+        let mut last = LastSeconds::new(seconds);
+        fired(|r| last.update(r), remaining)
+    }
+
+    #[test]
+    fn crossing_fires_once_when_reached() {
+        assert_eq!(crossing(60, &[62.0, 61.0, 60.5, 60.0, 59.0, 30.0]), [60.0]);
+        assert_eq!(crossing(0, &[1.0, 0.5, -0.1, -5.0]), [-0.1]);
+        // Skipping past the threshold in one step still fires.
+        assert_eq!(crossing(60, &[120.0, 10.0, 5.0]), [10.0]);
+    }
+
+    #[test]
+    fn crossing_ignores_thresholds_passed_before_the_first_update() {
+        assert!(crossing(60, &[30.0, 20.0, 10.0]).is_empty());
+        assert!(crossing(0, &[0.0, -1.0]).is_empty());
+    }
+
+    #[test]
+    fn crossing_rearms_when_time_rises_again() {
+        // A reset or a later target moves the remaining time back up.
+        assert_eq!(
+            crossing(60, &[61.0, 59.0, 300.0, 100.0, 60.0, 50.0]),
+            [59.0, 60.0]
+        );
+    }
+
+    #[test]
+    fn last_seconds_tick_on_each_second() {
+        assert_eq!(
+            last_seconds(3, &[5.0, 4.0, 3.5, 3.0, 2.9, 2.0, 1.0, 0.5, 0.0, -1.0]),
+            [3.0, 2.0, 1.0]
+        );
+        assert_eq!(last_seconds(3, &[3.2, 2.2, 1.2, 0.2]), [2.2, 1.2, 0.2]);
+        assert!(last_seconds(0, &[3.0, 2.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn last_seconds_skip_seconds_jumped_over() {
+        assert_eq!(last_seconds(5, &[10.0, 3.0, 2.0]), [3.0, 2.0]);
+        assert!(last_seconds(5, &[2.5, 2.2]).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::TimeUnit;
+use chrono::TimeDelta;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Invalid duration: '{duration}'")]
+pub struct InvalidDurationError {
+    pub duration: String,
+}
+
+/// Parses a duration written either compactly as numbers followed by units, like
+/// `25m`, `1h30m` or `2d 12h`, or in ISO 8601 form, like `PT90M` or `P1DT2H`. Units go
+/// from milliseconds up to weeks; months and years have no fixed length and are
+/// rejected. Only the seconds of an ISO duration may have a fraction.
+pub fn parse_duration(duration: &str) -> Result<TimeDelta, InvalidDurationError> {
+    let text = duration.trim();
+    let millis = match text.strip_prefix('P') {
+        Some(iso) => parse_iso(iso),
+        None => parse_compact(text),
+    };
+    millis
+        .filter(|millis| *millis >= 0)
+        .and_then(TimeDelta::try_milliseconds)
+        .ok_or_else(|| InvalidDurationError {
+            duration: duration.to_string(),
+        })
+}
+
+/// Sums `25m`, `1h30m`, `2d 12h` and the like, in milliseconds.
+fn parse_compact(text: &str) -> Option<i64> {
+    let mut rest = text;
+    let mut total: i64 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let letters = rest[digits..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .map_or(rest.len(), |i| digits + i);
+        let count: i64 = rest[..digits].parse().ok()?;
+        let unit: TimeUnit = rest[digits..letters].parse().ok()?;
+        if unit.calendar_months().is_some() {
+            return None;
+        }
+        total = total.checked_add(count.checked_mul(unit.millis())?)?;
+        rest = rest[letters..].trim_start();
+    }
+    (!text.is_empty()).then_some(total)
+}
+
+/// Sums the part of an ISO 8601 duration after the `P`, in milliseconds.
+fn parse_iso(iso: &str) -> Option<i64> {
+    let (date, time) = match iso.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (iso, None),
+    };
+    let mut total = sum_designators(date, &[('W', TimeUnit::Week), ('D', TimeUnit::Day)])?;
+    if let Some(time) = time {
+        if time.is_empty() {
+            return None;
+        }
+        let (time, seconds) = match time.strip_suffix('S') {
+            Some(time) => {
+                let start = time
+                    .rfind(|c: char| c.is_ascii_alphabetic())
+                    .map_or(0, |i| i + 1);
+                (&time[..start], Some(parse_seconds(&time[start..])?))
+            }
+            None => (time, None),
+        };
+        let hours_minutes = [('H', TimeUnit::Hour), ('M', TimeUnit::Minute)];
+        total = total.checked_add(sum_designators(time, &hours_minutes)?)?;
+        total = total.checked_add(seconds.unwrap_or(0))?;
+    }
+    (!date.is_empty() || time.is_some()).then_some(total)
+}
+
+/// Sums numbers each followed by one of `designators`, which must come in order.
+fn sum_designators(mut text: &str, designators: &[(char, TimeUnit)]) -> Option<i64> {
+    let mut total: i64 = 0;
+    let mut remaining = designators;
+    while !text.is_empty() {
+        let end = text.find(|c: char| !c.is_ascii_digit())?;
+        let designator = text[end..].chars().next()?;
+        let position = remaining.iter().position(|(d, _)| *d == designator)?;
+        let count: i64 = text[..end].parse().ok()?;
+        total = total.checked_add(count.checked_mul(remaining[position].1.millis())?)?;
+        remaining = &remaining[position + 1..];
+        text = &text[end + 1..];
+    }
+    Some(total)
+}
+
+/// Parses `90`, `1.5` or `0.250` seconds into milliseconds, dropping digits past them.
+fn parse_seconds(seconds: &str) -> Option<i64> {
+    let (whole, fraction) = seconds.split_once(['.', ',']).unwrap_or((seconds, ""));
+    if whole.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let whole: i64 = whole.parse().ok()?;
+    let millis: i64 = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse()
+        .ok()?;
+    whole.checked_mul(1000)?.checked_add(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_accepts() {
+        let cases = [
+            ("25m", TimeDelta::minutes(25)),
+            ("1h30m", TimeDelta::minutes(90)),
+            ("2d 12h", TimeDelta::hours(60)),
+            (" 90s ", TimeDelta::seconds(90)),
+            ("1w", TimeDelta::weeks(1)),
+            ("500ms", TimeDelta::milliseconds(500)),
+            ("0s", TimeDelta::zero()),
+            ("PT90M", TimeDelta::minutes(90)),
+            ("P1DT2H", TimeDelta::hours(26)),
+            ("P2W", TimeDelta::weeks(2)),
+            ("PT1H30M", TimeDelta::minutes(90)),
+            ("PT1.5S", TimeDelta::milliseconds(1500)),
+            ("PT0,25S", TimeDelta::milliseconds(250)),
+            ("PT1M0.1239S", TimeDelta::milliseconds(60_123)),
+        ];
+        for (duration, expected) in cases {
+            assert_eq!(parse_duration(duration).unwrap(), expected, "{duration}");
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects() {
+        for duration in [
+            "", "P", "PT", "1mo", "1y", "25", "m", "25x", "1.5h", "-5m", "PT1M1H", "PT5", "P1H",
+            "PT1S2M", "PT.5S", "P1DT", "pt90m",
+        ] {
+            let error = parse_duration(duration).unwrap_err();
+            assert_eq!(error.duration, duration);
+        }
+        let too_long = format!("{}w", i64::MAX / 1000);
+        assert!(parse_duration(too_long.as_str()).is_err());
+    }
+}
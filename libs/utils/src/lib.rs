@@ -1,9 +1,38 @@
 // Copyright (C) 2024 CaSilicate
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use chrono::{Datelike, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, LocalResult, Months, NaiveDate, NaiveDateTime, Offset,
+    TimeDelta, TimeZone, Timelike, Utc,
+};
+use chrono_tz::Tz;
+use std::str::FromStr;
 use thiserror::Error;
 
+mod breakdown;
+mod clock;
+mod control;
+mod crossing;
+mod duration;
+mod span;
+mod stopwatch;
+mod target;
+#[cfg(test)]
+mod test_support;
+mod tick;
+mod timeunit;
+
+pub use breakdown::{Breakdown, InvalidBreakdownError, format_breakdown};
+pub use clock::{Clock, FixedClock, OffsetClock, ScaledClock, SystemClock};
+pub use control::{PausableClock, TimerControl, TimerState};
+pub use crossing::{Crossing, LastSeconds};
+pub use duration::{InvalidDurationError, parse_duration};
+pub use span::{InvalidRoundingError, MAX_PRECISION, Rounding, UnitSpan};
+pub use stopwatch::{Lap, Stopwatch};
+pub use target::{Target, parse_target_at, target_zone};
+pub use tick::{TickSchedule, Ticker};
+pub use timeunit::TimeUnit;
+
 #[derive(Error, Debug)]
 #[error("Invalid unit: '{unit}',")]
 pub struct InvalidUnitError {
@@ -17,6 +46,116 @@ impl InvalidUnitError {
     }
 }
 
+#[derive(Error, Debug)]
+pub enum TargetParseError {
+    #[error("Unexpected '{token}' at column {column}, expected {expected}")]
+    Unexpected {
+        token: String,
+        column: usize,
+        expected: &'static str,
+    },
+    #[error("Unexpected end of target, expected {expected}")]
+    UnexpectedEnd { expected: &'static str },
+    #[error("Invalid time zone: '{0}'")]
+    TimeZone(String),
+    #[error(transparent)]
+    Duration(#[from] InvalidDurationError),
+}
+
+/// Time zone a target is written in. Targets without a zone use the system local zone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetZone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+impl FromStr for TargetZone {
+    type Err = TargetParseError;
+
+    fn from_str(zone: &str) -> Result<Self, Self::Err> {
+        if zone.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+        if zone == "Z" || zone.eq_ignore_ascii_case("utc") {
+            return Ok(Self::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+        if let Some(offset) = parse_offset(zone) {
+            return Ok(Self::Fixed(offset));
+        }
+        zone.parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| TargetParseError::TimeZone(zone.to_string()))
+    }
+}
+impl TargetZone {
+    pub fn resolve(&self, naive: &NaiveDateTime) -> DateTime<FixedOffset> {
+        match self {
+            Self::Local => resolve_local(&Local, naive).fixed_offset(),
+            Self::Fixed(offset) => resolve_local(offset, naive).fixed_offset(),
+            Self::Named(tz) => resolve_local(tz, naive).fixed_offset(),
+        }
+    }
+
+    /// The date it is at `now` in this zone.
+    pub fn today(&self, now: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Self::Local => now.with_timezone(&Local).date_naive(),
+            Self::Fixed(offset) => now.with_timezone(offset).date_naive(),
+            Self::Named(tz) => now.with_timezone(tz).date_naive(),
+        }
+    }
+}
+
+/// Parses `+08:00`, `-0530`, `+8` and the same forms prefixed with `UTC`/`GMT`.
+pub(crate) fn parse_offset(zone: &str) -> Option<FixedOffset> {
+    let zone = zone
+        .strip_prefix("UTC")
+        .or_else(|| zone.strip_prefix("GMT"))
+        .unwrap_or(zone);
+    let (sign, digits) = match zone.as_bytes().first()? {
+        b'+' => (1, &zone[1..]),
+        b'-' => (-1, &zone[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    if hours.is_empty() || hours.len() > 2 || minutes.len() > 2 {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Maps a wall-clock time to an instant in `tz`.
+///
+/// Ambiguous times (clocks turned back) resolve to the earlier instant. Times inside a
+/// gap (clocks turned forward) are shifted forward by the length of the gap, so
+/// `02:30` on a spring-forward night becomes `03:30` in the new offset.
+pub fn resolve_local<T: TimeZone>(tz: &T, naive: &NaiveDateTime) -> DateTime<T> {
+    match tz.from_local_datetime(naive) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => {
+            let before = tz
+                .offset_from_utc_datetime(&(*naive - TimeDelta::days(1)))
+                .fix();
+            tz.from_utc_datetime(&(*naive - before))
+        }
+    }
+}
+
+/// Parses a target time relative to the current system time; see `parse_target_at`.
+pub fn parse_target(target: &str) -> Result<DateTime<FixedOffset>, TargetParseError> {
+    parse_target_at(target, Utc::now())
+}
+
 #[derive(Debug)]
 pub struct SplitedTime {
     pub year: i32,
@@ -38,8 +177,9 @@ impl SplitedTime {
         }
     }
 
-    pub fn from_string(datetime: String) -> Result<Self, chrono::ParseError> {
-        let dt = NaiveDateTime::parse_from_str(datetime.as_str(), "%Y-%m-%d %H:%M:%S")?;
+    /// The wall-clock time a target is written in, in any form `parse_target` accepts.
+    pub fn from_string(datetime: String) -> Result<Self, TargetParseError> {
+        let dt = parse_target(datetime.as_str())?.naive_local();
         Ok(Self {
             year: dt.year(),
             month: dt.month() as i32,
@@ -58,40 +198,110 @@ impl SplitedTime {
     }
 }
 
-pub fn convert_timestamp(date_str: String) -> Result<i64, chrono::ParseError> {
-    let naive_datetime = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S")?;
-    let datetime_utc = Utc.from_local_datetime(&naive_datetime).unwrap();
-    let timestamp = datetime_utc.timestamp_millis();
-    Ok(timestamp - ((8 * 3600 * 1000) as i64))
+pub fn format_zeros(number: f64, decimals: i32) -> String {
+    format!("{:.decimals$}", number, decimals = decimals as usize)
 }
 
-pub fn advanced_round(input: f64, precision: i32) -> f64 {
-    let factor = 10_f64.powi(precision);
-    (input * factor).round() / factor
+pub fn convert_time_unit(timems: f64, unit: TimeUnit) -> f64 {
+    timems / unit.millis() as f64
 }
 
-pub fn format_zeros(number: f64, decimals: i32) -> String {
-    format!("{:.decimals$}", number, decimals = decimals as usize)
+pub(crate) fn add_calendar_months(
+    from: DateTime<FixedOffset>,
+    months: i64,
+) -> Option<DateTime<FixedOffset>> {
+    u32::try_from(months)
+        .ok()
+        .and_then(|months| from.checked_add_months(Months::new(months)))
 }
 
-pub fn convert_time_unit(timems: f64, unit: &str) -> Result<f64, InvalidUnitError> {
-    if unit == "ms" {
-        Ok(timems)
-    } else if unit == "s" {
-        Ok(timems / (1_i64 * 1000) as f64)
-    } else if unit == "m" {
-        Ok(timems / (1_i64 * 1000 * 60) as f64)
-    } else if unit == "h" {
-        Ok(timems / (1_i64 * 1000 * 60 * 60) as f64)
-    } else if unit == "d" {
-        Ok(timems / (1_i64 * 1000 * 60 * 60 * 24) as f64)
-    } else if unit == "w" {
-        Ok(timems / (1_i64 * 1000 * 60 * 60 * 24 * 7) as f64)
-    } else if unit == "mo" {
-        Ok(timems / (1_i64 * 1000 * 60 * 60 * 24 * 30) as f64)
-    } else if unit == "y" {
-        Ok(timems / (1_i64 * 1000 * 60 * 60 * 24 * 365) as f64)
-    } else {
-        Err(InvalidUnitError::new(unit))
+/// Counts how many whole blocks of `months_per_unit` calendar months fit between `from`
+/// and `to` (`from <= to`), returning the count and the instant the last block ends.
+pub(crate) fn whole_calendar_units(
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+    months_per_unit: i64,
+) -> (i64, DateTime<FixedOffset>) {
+    let add_units = |n: i64| add_calendar_months(from, n * months_per_unit);
+
+    let month_diff =
+        (to.year() - from.year()) as i64 * 12 + to.month() as i64 - from.month() as i64;
+    let mut whole = (month_diff / months_per_unit).max(0);
+    while whole > 0 && add_units(whole).is_none_or(|dt| dt > to) {
+        whole -= 1;
+    }
+    while add_units(whole + 1).is_some_and(|dt| dt <= to) {
+        whole += 1;
+    }
+    (whole, add_units(whole).unwrap_or(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn berlin(naive: &str) -> String {
+        let naive = NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M").unwrap();
+        resolve_local(&Berlin, &naive).fixed_offset().to_rfc3339()
+    }
+
+    #[test]
+    fn resolve_local_shifts_times_in_a_gap_forward() {
+        assert_eq!(berlin("2026-03-29 02:30"), "2026-03-29T03:30:00+02:00");
+        assert_eq!(berlin("2026-03-29 01:59"), "2026-03-29T01:59:00+01:00");
+        assert_eq!(berlin("2026-03-29 03:00"), "2026-03-29T03:00:00+02:00");
+    }
+
+    #[test]
+    fn resolve_local_picks_the_earlier_of_ambiguous_times() {
+        assert_eq!(berlin("2026-10-25 02:30"), "2026-10-25T02:30:00+02:00");
+        assert_eq!(berlin("2026-10-25 03:00"), "2026-10-25T03:00:00+01:00");
+    }
+
+    #[test]
+    fn target_zone_resolves_named_zones() {
+        let zone: TargetZone = "Europe/Berlin".parse().unwrap();
+        assert_eq!(zone, TargetZone::Named(Berlin));
+        let naive = NaiveDateTime::parse_from_str("2026-03-29 02:30", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(
+            zone.resolve(&naive).to_rfc3339(),
+            "2026-03-29T03:30:00+02:00"
+        );
+        assert!(matches!(
+            "Mars/Olympus".parse::<TargetZone>(),
+            Err(TargetParseError::TimeZone(_))
+        ));
+    }
+
+    #[test]
+    fn parse_offset_accepts() {
+        let cases = [
+            ("+08:00", 8 * 3600),
+            ("-05:30", -(5 * 3600 + 30 * 60)),
+            ("-0530", -(5 * 3600 + 30 * 60)),
+            ("+8", 8 * 3600),
+            ("+08", 8 * 3600),
+            ("UTC+8", 8 * 3600),
+            ("GMT-03:30", -(3 * 3600 + 30 * 60)),
+            ("+00:00", 0),
+            ("-23:59", -(23 * 3600 + 59 * 60)),
+        ];
+        for (zone, seconds) in cases {
+            assert_eq!(
+                parse_offset(zone).map(|o| o.local_minus_utc()),
+                Some(seconds),
+                "{zone}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_offset_rejects() {
+        for zone in [
+            "", "+", "08:00", "UTC", "+123", "+08:60", "+08:0000", "+24", "+ab", "utc+8", "+08:",
+        ] {
+            assert_eq!(parse_offset(zone), None, "{zone}");
+        }
     }
 }
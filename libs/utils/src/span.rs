@@ -0,0 +1,324 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{TimeUnit, add_calendar_months, whole_calendar_units};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Invalid rounding: '{rounding}'")]
+pub struct InvalidRoundingError {
+    pub rounding: String,
+}
+
+/// How a span is rounded to the displayed number of decimals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    /// Towards negative infinity.
+    Floor,
+    /// Towards positive infinity, so a countdown only shows zero once it is reached.
+    #[default]
+    Ceil,
+    /// To the nearest value, halves away from zero.
+    Nearest,
+}
+impl Rounding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rounding::Floor => "floor",
+            Rounding::Ceil => "ceil",
+            Rounding::Nearest => "nearest",
+        }
+    }
+
+    /// Swaps floor and ceil, for spans that count up rather than down.
+    pub fn mirrored(self) -> Self {
+        match self {
+            Rounding::Floor => Rounding::Ceil,
+            Rounding::Ceil => Rounding::Floor,
+            Rounding::Nearest => Rounding::Nearest,
+        }
+    }
+
+    /// Divides `n` by a positive `d`.
+    fn divide(self, n: i128, d: i128) -> i128 {
+        let floor = n.div_euclid(d);
+        let rem = n.rem_euclid(d);
+        let round_up = match self {
+            Rounding::Floor => false,
+            Rounding::Ceil => rem != 0,
+            Rounding::Nearest => 2 * rem > d || (2 * rem == d && n > 0),
+        };
+        floor + round_up as i128
+    }
+}
+impl FromStr for Rounding {
+    type Err = InvalidRoundingError;
+
+    fn from_str(rounding: &str) -> Result<Self, Self::Err> {
+        [Rounding::Floor, Rounding::Ceil, Rounding::Nearest]
+            .into_iter()
+            .find(|r| r.as_str() == rounding)
+            .ok_or_else(|| InvalidRoundingError {
+                rounding: rounding.to_string(),
+            })
+    }
+}
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The most decimals `UnitSpan::format` can compute exactly.
+pub const MAX_PRECISION: u32 = 20;
+
+/// A span of time in some unit, kept as the exact fraction `numerator / denominator`
+/// of milliseconds so it can be rounded without floating-point error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitSpan {
+    numerator: i128,
+    denominator: i128,
+}
+impl UnitSpan {
+    /// The span from `from` to `to` in `unit`.
+    ///
+    /// With `calendar` set, months and years walk the real calendar in the zone of `to`,
+    /// so a span of one calendar month or year is exactly `1`. Otherwise every unit has
+    /// a fixed length (30-day months, 365-day years).
+    pub fn new(
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+        unit: TimeUnit,
+        calendar: bool,
+    ) -> Self {
+        let months_per_unit = match unit.calendar_months() {
+            Some(months) if calendar => months,
+            _ => {
+                return Self {
+                    numerator: (to - from).num_milliseconds() as i128,
+                    denominator: unit.millis() as i128,
+                };
+            }
+        };
+        if to < from {
+            let span = Self::new(to, from, unit, calendar);
+            return Self {
+                numerator: -span.numerator,
+                ..span
+            };
+        }
+        let from = from.with_timezone(&to.timezone());
+        let (whole, start) = whole_calendar_units(from, to, months_per_unit);
+        let length = add_calendar_months(from, (whole + 1) * months_per_unit)
+            .map_or(0, |end| (end - start).num_milliseconds());
+        if length <= 0 {
+            return Self {
+                numerator: whole as i128,
+                denominator: 1,
+            };
+        }
+        let length = length as i128;
+        Self {
+            numerator: whole as i128 * length + (to - start).num_milliseconds() as i128,
+            denominator: length,
+        }
+    }
+
+    /// The span times `10^precision`, rounded to an integer.
+    pub fn scaled(self, precision: u32, rounding: Rounding) -> i128 {
+        let factor = 10_i128.pow(precision.min(MAX_PRECISION));
+        rounding.divide(self.numerator * factor, self.denominator)
+    }
+
+    /// Formats the span with exactly `precision` decimals, at most `MAX_PRECISION`.
+    pub fn format(self, precision: u32, rounding: Rounding) -> String {
+        let precision = precision.min(MAX_PRECISION);
+        let scaled = self.scaled(precision, rounding);
+        let sign = if scaled < 0 { "-" } else { "" };
+        let digits = scaled.unsigned_abs().to_string();
+        if precision == 0 {
+            return format!("{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = precision as usize + 1);
+        let (int, frac) = digits.split_at(digits.len() - precision as usize);
+        format!("{}{}.{}", sign, int, frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Lcg;
+    use chrono::{TimeDelta, Utc};
+
+    /// Rounds `n / d` with truncating division instead of `div_euclid`.
+    fn reference_divide(n: i128, d: i128, rounding: Rounding) -> i128 {
+        let (q, r) = (n / d, n % d);
+        match rounding {
+            Rounding::Floor if r < 0 => q - 1,
+            Rounding::Ceil if r > 0 => q + 1,
+            Rounding::Nearest if 2 * r.abs() >= d => q + n.signum(),
+            _ => q,
+        }
+    }
+
+    /// Formats a scaled integer with `precision` decimals by inserting the point.
+    fn reference_format(scaled: i128, precision: u32) -> String {
+        let digits = format!("{:0>1$}", scaled.unsigned_abs(), precision as usize + 1);
+        let point = digits.len() - precision as usize;
+        let sign = if scaled < 0 { "-" } else { "" };
+        if precision == 0 {
+            return format!("{sign}{digits}");
+        }
+        format!("{sign}{}.{}", &digits[..point], &digits[point..])
+    }
+
+    const ROUNDINGS: [Rounding; 3] = [Rounding::Floor, Rounding::Ceil, Rounding::Nearest];
+
+    fn origin() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-06-15T12:00:00+08:00").unwrap()
+    }
+
+    #[test]
+    fn fixed_units_match_the_reference() {
+        let mut rng = Lcg(7);
+        for _ in 0..5000 {
+            // Spans up to about 30 years either way, biased towards small ones.
+            let magnitude = 10_i64.pow(rng.below(13) as u32);
+            let millis = rng.below(magnitude as u64) as i64 - magnitude / 2;
+            let unit = TimeUnit::ALL[rng.below(TimeUnit::ALL.len() as u64) as usize];
+            let precision = rng.below(MAX_PRECISION as u64 + 1) as u32;
+            let from = origin();
+            let to = from + TimeDelta::milliseconds(millis);
+            let span = UnitSpan::new(from, to, unit, false);
+            for rounding in ROUNDINGS {
+                let expected = reference_divide(
+                    millis as i128 * 10_i128.pow(precision),
+                    unit.millis() as i128,
+                    rounding,
+                );
+                let case = format!("{millis} ms in {unit} to {precision} places, {rounding}");
+                assert_eq!(span.scaled(precision, rounding), expected, "{case}");
+                assert_eq!(
+                    span.format(precision, rounding),
+                    reference_format(expected, precision),
+                    "{case}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn negative_spans_mirror_positive_ones() {
+        let mut rng = Lcg(11);
+        for _ in 0..2000 {
+            let millis = rng.below(1_000_000_000) as i64;
+            let unit = TimeUnit::ALL[rng.below(TimeUnit::ALL.len() as u64) as usize];
+            let calendar = rng.below(2) == 0;
+            let precision = rng.below(6) as u32;
+            let from = origin();
+            let to = from + TimeDelta::milliseconds(millis);
+            let ahead = UnitSpan::new(from, to, unit, calendar);
+            let behind = UnitSpan::new(to, from, unit, calendar);
+            for rounding in ROUNDINGS {
+                assert_eq!(
+                    behind.scaled(precision, rounding.mirrored()),
+                    -ahead.scaled(precision, rounding),
+                    "{millis} ms in {unit}, {rounding}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_rounds_ties_away_from_zero() {
+        let cases = [
+            (1500, 0, "2"),
+            (-1500, 0, "-2"),
+            (2500, 0, "3"),
+            (-2500, 0, "-3"),
+            (1499, 0, "1"),
+            (-1499, 0, "-1"),
+            (1250, 1, "1.3"),
+            (-1250, 1, "-1.3"),
+            (500, 0, "1"),
+            (-500, 0, "-1"),
+            (-499, 0, "0"),
+        ];
+        for (millis, precision, expected) in cases {
+            let to = origin() + TimeDelta::milliseconds(millis);
+            let span = UnitSpan::new(origin(), to, TimeUnit::Second, false);
+            assert_eq!(
+                span.format(precision, Rounding::Nearest),
+                expected,
+                "{millis}"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_is_never_negative() {
+        let to = origin() - TimeDelta::milliseconds(400);
+        let span = UnitSpan::new(origin(), to, TimeUnit::Second, false);
+        assert_eq!(span.format(0, Rounding::Ceil), "0");
+        assert_eq!(span.format(0, Rounding::Nearest), "0");
+        assert_eq!(span.format(0, Rounding::Floor), "-1");
+    }
+
+    #[test]
+    fn widest_spans_do_not_overflow_at_max_precision() {
+        let min = DateTime::<Utc>::MIN_UTC.fixed_offset();
+        let max = DateTime::<Utc>::MAX_UTC.fixed_offset();
+        let millis = (max - min).num_milliseconds() as i128;
+        for unit in TimeUnit::ALL {
+            for calendar in [false, true] {
+                for (from, to, sign) in [(min, max, 1), (max, min, -1)] {
+                    let span = UnitSpan::new(from, to, unit, calendar);
+                    for rounding in ROUNDINGS {
+                        let scaled = span.scaled(MAX_PRECISION, rounding);
+                        assert_eq!(scaled.signum(), sign, "{unit} {calendar}");
+                        if unit == TimeUnit::Millisecond {
+                            assert_eq!(scaled, sign * millis * 10_i128.pow(MAX_PRECISION));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn precision_is_capped() {
+        let to = origin() + TimeDelta::milliseconds(1);
+        let span = UnitSpan::new(origin(), to, TimeUnit::Second, false);
+        let formatted = span.format(MAX_PRECISION + 5, Rounding::Floor);
+        assert_eq!(formatted, format!("0.001{}", "0".repeat(17)));
+        assert_eq!(
+            span.scaled(MAX_PRECISION + 5, Rounding::Floor),
+            span.scaled(MAX_PRECISION, Rounding::Floor)
+        );
+    }
+
+    #[test]
+    fn calendar_units_count_whole_months() {
+        let from = origin();
+        for months in [0, 1, 2, 11, 12, 13, 25, 120] {
+            let to = add_calendar_months(from, months).unwrap();
+            let span = UnitSpan::new(from, to, TimeUnit::Month, true);
+            assert_eq!(span.format(3, Rounding::Ceil), format!("{months}.000"));
+            let years = UnitSpan::new(from, to, TimeUnit::Year, true);
+            assert_eq!(years.scaled(0, Rounding::Floor), (months / 12) as i128);
+            let back = UnitSpan::new(to, from, TimeUnit::Month, true);
+            let expected = if months == 0 {
+                "0.000".to_string()
+            } else {
+                format!("-{months}.000")
+            };
+            assert_eq!(back.format(3, Rounding::Floor), expected);
+        }
+    }
+}
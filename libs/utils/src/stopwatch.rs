@@ -0,0 +1,235 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{TimerControl, TimerState};
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// One lap of a `Stopwatch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lap {
+    /// Counted from 1.
+    pub number: usize,
+    /// Time since the previous lap.
+    pub lap: TimeDelta,
+    /// Time since the start.
+    pub split: TimeDelta,
+}
+
+/// A stopwatch that starts stopped at zero and records laps. Time only runs while
+/// it is started, as kept by a `TimerControl`.
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    control: TimerControl,
+    splits: Vec<TimeDelta>,
+}
+impl Stopwatch {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        let mut control = TimerControl::new(now);
+        control.pause(now);
+        Self {
+            control,
+            splits: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> TimerState {
+        self.control.state()
+    }
+
+    /// Starts if stopped and stops if running, returning the new state.
+    pub fn toggle(&mut self, now: DateTime<Utc>) -> TimerState {
+        self.control.toggle(now)
+    }
+
+    /// Stops at zero and forgets every lap.
+    pub fn reset(&mut self, now: DateTime<Utc>) {
+        *self = Self::new(now);
+    }
+
+    pub fn elapsed(&self, now: DateTime<Utc>) -> TimeDelta {
+        self.control.elapsed(now)
+    }
+
+    /// Ends a lap at `now`. Laps are only recorded while running.
+    pub fn lap(&mut self, now: DateTime<Utc>) -> Option<Lap> {
+        if self.state() != TimerState::Running {
+            return None;
+        }
+        self.splits.push(self.elapsed(now));
+        self.laps().last().copied()
+    }
+
+    /// Every lap so far, oldest first.
+    pub fn laps(&self) -> Vec<Lap> {
+        let mut previous = TimeDelta::zero();
+        self.splits
+            .iter()
+            .enumerate()
+            .map(|(i, split)| {
+                let lap = Lap {
+                    number: i + 1,
+                    lap: *split - previous,
+                    split: *split,
+                };
+                previous = *split;
+                lap
+            })
+            .collect()
+    }
+
+    /// The laps as CSV with a header row, times in milliseconds.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("lap,lap_ms,split_ms\n");
+        for lap in self.laps() {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                lap.number,
+                lap.lap.num_milliseconds(),
+                lap.split.num_milliseconds()
+            ));
+        }
+        csv
+    }
+
+    /// The laps as a JSON array with the same fields as `to_csv`.
+    pub fn to_json(&self) -> String {
+        let laps: Vec<String> = self
+            .laps()
+            .iter()
+            .map(|lap| {
+                format!(
+                    "  {{\"lap\": {}, \"lap_ms\": {}, \"split_ms\": {}}}",
+                    lap.number,
+                    lap.lap.num_milliseconds(),
+                    lap.split.num_milliseconds()
+                )
+            })
+            .collect();
+        if laps.is_empty() {
+            return "[]\n".to_string();
+        }
+        format!("[\n{}\n]\n", laps.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_798_732_800_000 + millis).unwrap()
+    }
+
+    fn ms(millis: i64) -> TimeDelta {
+        TimeDelta::milliseconds(millis)
+    }
+
+    /// Started at 0, lapped at 1.5s, stopped from 2s to 5s, lapped at 5.25s and 6s.
+    fn three_laps() -> Stopwatch {
+        let mut stopwatch = Stopwatch::new(at(0));
+        stopwatch.toggle(at(0));
+        stopwatch.lap(at(1500));
+        stopwatch.toggle(at(2000));
+        stopwatch.toggle(at(5000));
+        stopwatch.lap(at(5250));
+        stopwatch.lap(at(6000));
+        stopwatch
+    }
+
+    #[test]
+    fn starts_stopped_at_zero() {
+        let stopwatch = Stopwatch::new(at(0));
+        assert_eq!(stopwatch.state(), TimerState::Paused);
+        assert_eq!(stopwatch.elapsed(at(5000)), TimeDelta::zero());
+        assert!(stopwatch.laps().is_empty());
+    }
+
+    #[test]
+    fn runs_only_while_started() {
+        let mut stopwatch = Stopwatch::new(at(0));
+        assert_eq!(stopwatch.toggle(at(1000)), TimerState::Running);
+        assert_eq!(stopwatch.elapsed(at(3000)), ms(2000));
+        assert_eq!(stopwatch.toggle(at(3000)), TimerState::Paused);
+        assert_eq!(stopwatch.elapsed(at(9000)), ms(2000));
+        stopwatch.toggle(at(9000));
+        assert_eq!(stopwatch.elapsed(at(9500)), ms(2500));
+    }
+
+    #[test]
+    fn laps_leave_out_the_stopped_time() {
+        let stopwatch = three_laps();
+        let expected = [(1, 1500, 1500), (2, 750, 2250), (3, 750, 3000)];
+        let expected = expected.map(|(number, lap, split)| Lap {
+            number,
+            lap: ms(lap),
+            split: ms(split),
+        });
+        assert_eq!(stopwatch.laps(), expected);
+    }
+
+    #[test]
+    fn lap_returns_the_new_lap() {
+        let mut stopwatch = Stopwatch::new(at(0));
+        stopwatch.toggle(at(0));
+        let first = stopwatch.lap(at(1000)).unwrap();
+        assert_eq!(
+            (first.number, first.lap, first.split),
+            (1, ms(1000), ms(1000))
+        );
+        let second = stopwatch.lap(at(1000)).unwrap();
+        assert_eq!(
+            (second.number, second.lap, second.split),
+            (2, ms(0), ms(1000))
+        );
+    }
+
+    #[test]
+    fn lap_while_stopped_records_nothing() {
+        let mut stopwatch = Stopwatch::new(at(0));
+        assert_eq!(stopwatch.lap(at(1000)), None);
+        stopwatch.toggle(at(1000));
+        stopwatch.lap(at(2000));
+        stopwatch.toggle(at(3000));
+        assert_eq!(stopwatch.lap(at(4000)), None);
+        assert_eq!(stopwatch.laps().len(), 1);
+    }
+
+    #[test]
+    fn reset_stops_at_zero_and_forgets_the_laps() {
+        let mut stopwatch = three_laps();
+        stopwatch.reset(at(7000));
+        assert_eq!(stopwatch.state(), TimerState::Paused);
+        assert_eq!(stopwatch.elapsed(at(8000)), TimeDelta::zero());
+        assert!(stopwatch.laps().is_empty());
+
+        stopwatch.toggle(at(8000));
+        let lap = stopwatch.lap(at(8500)).unwrap();
+        assert_eq!((lap.number, lap.split), (1, ms(500)));
+    }
+
+    #[test]
+    fn exports_csv() {
+        assert_eq!(
+            three_laps().to_csv(),
+            "lap,lap_ms,split_ms\n1,1500,1500\n2,750,2250\n3,750,3000\n"
+        );
+        assert_eq!(Stopwatch::new(at(0)).to_csv(), "lap,lap_ms,split_ms\n");
+    }
+
+    #[test]
+    fn exports_json() {
+        let json = three_laps().to_json();
+        assert_eq!(
+            json,
+            "[\n  {\"lap\": 1, \"lap_ms\": 1500, \"split_ms\": 1500},\n  \
+             {\"lap\": 2, \"lap_ms\": 750, \"split_ms\": 2250},\n  \
+             {\"lap\": 3, \"lap_ms\": 750, \"split_ms\": 3000}\n]\n"
+        );
+        // JSON is YAML, so this checks it is well formed.
+        let parsed: Vec<std::collections::BTreeMap<String, i64>> =
+            serde_yaml::from_str(json.as_str()).unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[1]["split_ms"], 2250);
+        assert_eq!(Stopwatch::new(at(0)).to_json(), "[]\n");
+    }
+}
@@ -0,0 +1,486 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{TargetParseError, TargetZone, parse_duration, parse_offset};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveTime, TimeDelta, Utc, Weekday,
+};
+use std::str::FromStr;
+
+/// What a countdown counts towards: a point in time, or a duration measured from
+/// whenever the timer starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    At(DateTime<FixedOffset>),
+    After(TimeDelta),
+}
+impl Target {
+    /// Parses a duration like `25m` or `PT90M`, or else anything `parse_target_at`
+    /// accepts, with relative dates counted from `now`.
+    pub fn parse_at(target: &str, now: DateTime<Utc>) -> Result<Self, TargetParseError> {
+        let duration = match parse_duration(target) {
+            Ok(duration) => return Ok(Target::After(duration)),
+            Err(e) => e,
+        };
+        match parse_target_at(target, now) {
+            Ok(at) => Ok(Target::At(at)),
+            Err(_) if looks_like_duration(target) => Err(duration.into()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The deadline for a timer started at `start`.
+    pub fn resolve(&self, start: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            Target::At(at) => *at,
+            Target::After(duration) => start + *duration,
+        }
+    }
+}
+impl FromStr for Target {
+    type Err = TargetParseError;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        Self::parse_at(target, Utc::now())
+    }
+}
+
+/// Whether a target that is neither a valid duration nor a valid date was probably
+/// meant as a duration, such as `25x` or `PT5`, to pick the more helpful error.
+fn looks_like_duration(target: &str) -> bool {
+    let target = target.trim();
+    !target.contains(['-', ':', ' '])
+        && target.starts_with(|c: char| c.is_ascii_digit() || c == 'P')
+        && !target.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A word of the target and the column it starts at, counted from 1.
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+impl Token<'_> {
+    fn unexpected(&self, expected: &'static str) -> TargetParseError {
+        TargetParseError::Unexpected {
+            token: self.text.to_string(),
+            column: self.column,
+            expected,
+        }
+    }
+}
+
+fn tokenize(target: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (column, (i, c)) in target
+        .char_indices()
+        .chain([(target.len(), ' ')])
+        .enumerate()
+    {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((i, column + 1)),
+            (Some((begin, column)), true) => {
+                tokens.push(Token {
+                    text: &target[begin..i],
+                    column,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// The day a target falls on, before the time zone is known.
+enum Day {
+    Date(NaiveDate),
+    /// Days after today.
+    Relative(i64),
+    /// The first such weekday after today, or from today on if `include_today`.
+    Weekday {
+        weekday: Weekday,
+        include_today: bool,
+    },
+}
+impl Day {
+    fn resolve(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Day::Date(date) => Some(*date),
+            Day::Relative(days) => today.checked_add_signed(TimeDelta::days(*days)),
+            Day::Weekday {
+                weekday,
+                include_today,
+            } => {
+                let ahead = (7 + weekday.num_days_from_monday() as i64
+                    - today.weekday().num_days_from_monday() as i64)
+                    % 7;
+                let ahead = if ahead == 0 && !include_today {
+                    7
+                } else {
+                    ahead
+                };
+                today.checked_add_signed(TimeDelta::days(ahead))
+            }
+        }
+    }
+}
+
+const EXPECTED_DAY: &str = "a date like 2026-12-31 or 20261231, a time, a weekday, 'today', \
+                            'tomorrow', 'next <weekday>' or a Unix timestamp";
+const EXPECTED_TIME: &str = "a time like 17:00 or 17:00:30";
+
+/// Parses a target time in any of these forms, each optionally followed by a time zone
+/// (`Europe/Berlin`, `UTC+8`, `local`, ...) unless it already carries an offset:
+///
+/// - `2026-12-31 23:59:00`, `2026-12-31 23:59` or just `2026-12-31` (midnight)
+/// - ISO 8601 and RFC 3339, like `2026-12-31T23:59`, `20261231` or
+///   `2026-12-31T23:59:00+08:00`
+/// - a Unix timestamp in seconds, like `1798732800` or `@1798732800`; without the `@`
+///   it needs at least 9 digits, so that 8 digits are a date
+/// - relative to `now`: `today`, `tomorrow`, `yesterday`, a weekday (the next one,
+///   today included) or `next <weekday>` (today excluded), optionally followed by a
+///   time, or a time alone for today, like `tomorrow 09:00` or `next friday 17:00`
+///
+/// Without a zone, times are in the system local zone. Errors name the offending
+/// word and its column.
+pub fn parse_target_at(
+    target: &str,
+    now: DateTime<Utc>,
+) -> Result<DateTime<FixedOffset>, TargetParseError> {
+    parse_with_zone(target, now).map(|(at, _)| at)
+}
+
+/// The time zone or UTC offset `target` is written with, like `Europe/Berlin` or
+/// `+08:00`, or `None` if it has none or is not a valid point in time.
+pub fn target_zone(target: &str) -> Option<&str> {
+    parse_with_zone(target, Utc::now()).ok()?.1
+}
+
+/// `parse_target_at`, also returning the zone as written.
+fn parse_with_zone(
+    target: &str,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<FixedOffset>, Option<&str>), TargetParseError> {
+    let tokens = tokenize(target);
+    let mut tokens = tokens.iter().peekable();
+    let Some(first) = tokens.next() else {
+        return Err(TargetParseError::UnexpectedEnd {
+            expected: EXPECTED_DAY,
+        });
+    };
+
+    if let Some(seconds) = parse_timestamp(first.text) {
+        if let Some(extra) = tokens.next() {
+            return Err(extra.unexpected("nothing after a Unix timestamp"));
+        }
+        let at = DateTime::from_timestamp(seconds, 0)
+            .ok_or_else(|| first.unexpected("a Unix timestamp in range"))?;
+        return Ok((at.with_timezone(&Local).fixed_offset(), None));
+    }
+
+    let mut time = None;
+    let mut offset = None;
+    let lower = first.text.to_ascii_lowercase();
+    let day = match lower.as_str() {
+        "today" => Day::Relative(0),
+        "tomorrow" => Day::Relative(1),
+        "yesterday" => Day::Relative(-1),
+        "next" => {
+            let Some(token) = tokens.next() else {
+                return Err(TargetParseError::UnexpectedEnd {
+                    expected: "a weekday after 'next'",
+                });
+            };
+            let weekday = parse_weekday(token.text)
+                .ok_or_else(|| token.unexpected("a weekday after 'next'"))?;
+            Day::Weekday {
+                weekday,
+                include_today: false,
+            }
+        }
+        _ => {
+            if let Some(weekday) = parse_weekday(first.text) {
+                Day::Weekday {
+                    weekday,
+                    include_today: true,
+                }
+            } else if first.text.starts_with(|c: char| c.is_ascii_digit()) {
+                let (date, rest) = match first.text.split_once(['T', 't']) {
+                    Some((date, rest)) => (date, Some(rest)),
+                    None => (first.text, None),
+                };
+                if rest.is_none() && date.contains(':') {
+                    // A time alone is today.
+                    (time, offset) = parse_time(first, first.text)?;
+                    Day::Relative(0)
+                } else {
+                    let date = parse_date(date).ok_or_else(|| first.unexpected(EXPECTED_DAY))?;
+                    if let Some(rest) = rest {
+                        (time, offset) = parse_time(first, rest)?;
+                    }
+                    Day::Date(date)
+                }
+            } else {
+                return Err(first.unexpected(EXPECTED_DAY));
+            }
+        }
+    };
+
+    if time.is_none()
+        && let Some(token) = tokens.next_if(|t| t.text.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        (time, offset) = parse_time(token, token.text)?;
+    }
+    let (zone, zone_text) = match (offset, tokens.next()) {
+        (Some((offset, text)), None) => (TargetZone::Fixed(offset), Some(text)),
+        (Some(_), Some(token)) => {
+            return Err(token.unexpected("nothing after a UTC offset"));
+        }
+        (None, Some(token)) => {
+            let zone = token
+                .text
+                .parse()
+                .map_err(|_| token.unexpected("a time zone"))?;
+            (zone, Some(token.text))
+        }
+        (None, None) => (TargetZone::Local, None),
+    };
+    if let Some(extra) = tokens.next() {
+        return Err(extra.unexpected("nothing after the time zone"));
+    }
+
+    let date = day
+        .resolve(zone.today(now))
+        .ok_or_else(|| first.unexpected("a date in range"))?;
+    let at = zone.resolve(&date.and_time(time.unwrap_or(NaiveTime::MIN)));
+    Ok((at, zone_text))
+}
+
+/// `1798732800` or `@1798732800`. Without the `@`, numbers shorter than 9 digits are
+/// left for `parse_date`.
+fn parse_timestamp(text: &str) -> Option<i64> {
+    let (digits, min_len) = match text.strip_prefix('@') {
+        Some(digits) => (digits, 1),
+        None => (text, 9),
+    };
+    if digits.len() < min_len || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// `2026-12-31` or `20261231`.
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    if text.len() == 8 && text.chars().all(|c| c.is_ascii_digit()) {
+        let number = |range: std::ops::Range<usize>| text[range].parse().ok();
+        return NaiveDate::from_ymd_opt(number(0..4)? as i32, number(4..6)?, number(6..8)?);
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+/// Full English weekday names and their three-letter forms, in any case.
+fn parse_weekday(text: &str) -> Option<Weekday> {
+    text.parse().ok()
+}
+
+/// A UTC offset and how it was written.
+type WrittenOffset<'a> = (FixedOffset, &'a str);
+
+/// Parses `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fff`, optionally followed by `Z` or an
+/// offset like `+08:00`, which is returned as text too. `text` is the part of `token`
+/// that holds the time.
+fn parse_time<'a>(
+    token: &Token,
+    text: &'a str,
+) -> Result<(Option<NaiveTime>, Option<WrittenOffset<'a>>), TargetParseError> {
+    let (clock, offset) = match text.find(['+', '-', 'Z', 'z']) {
+        Some(i) => (&text[..i], Some(&text[i..])),
+        None => (text, None),
+    };
+    let offset = match offset {
+        Some("Z" | "z") => Some((FixedOffset::east_opt(0).unwrap(), "Z")),
+        Some(text) => {
+            let offset =
+                parse_offset(text).ok_or_else(|| token.unexpected("a UTC offset like +08:00"))?;
+            Some((offset, text))
+        }
+        None => None,
+    };
+    let time = NaiveTime::parse_from_str(clock, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(clock, "%H:%M"))
+        .map_err(|_| token.unexpected(EXPECTED_TIME))?;
+    Ok((Some(time), offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Offset, TimeZone};
+
+    /// A Sunday morning.
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-10-18T10:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    #[test]
+    fn accepts() {
+        let cases = [
+            ("2026-12-31 23:59:00 UTC", "2026-12-31T23:59:00+00:00"),
+            ("2026-12-31 23:59 +08:00", "2026-12-31T23:59:00+08:00"),
+            ("2026-12-31 UTC", "2026-12-31T00:00:00+00:00"),
+            ("20261231 UTC", "2026-12-31T00:00:00+00:00"),
+            (
+                "2026-12-31T23:59 Europe/Berlin",
+                "2026-12-31T23:59:00+01:00",
+            ),
+            ("2026-12-31t23:59:00 UTC+8", "2026-12-31T23:59:00+08:00"),
+            ("2026-12-31 23:59 GMT-03:30", "2026-12-31T23:59:00-03:30"),
+            ("2026-12-31T23:59:00+08:00", "2026-12-31T23:59:00+08:00"),
+            ("2026-12-31T23:59:00.5Z", "2026-12-31T23:59:00.500+00:00"),
+            ("  2026-12-31   23:59\tutc ", "2026-12-31T23:59:00+00:00"),
+            ("today UTC", "2026-10-18T00:00:00+00:00"),
+            ("Tomorrow 09:00 UTC", "2026-10-19T09:00:00+00:00"),
+            ("yesterday 23:00 Asia/Shanghai", "2026-10-17T23:00:00+08:00"),
+            (
+                "tomorrow 01:00 Pacific/Kiritimati",
+                "2026-10-20T01:00:00+14:00",
+            ),
+            ("sunday 17:00 UTC", "2026-10-18T17:00:00+00:00"),
+            ("next sunday 17:00 UTC", "2026-10-25T17:00:00+00:00"),
+            ("monday UTC", "2026-10-19T00:00:00+00:00"),
+            ("Fri 17:00 UTC", "2026-10-23T17:00:00+00:00"),
+            ("NEXT friday 17:00 UTC", "2026-10-23T17:00:00+00:00"),
+            ("17:00 UTC", "2026-10-18T17:00:00+00:00"),
+            ("17:00:30+02:00", "2026-10-18T17:00:30+02:00"),
+        ];
+        for (target, expected) in cases {
+            let at = parse_target_at(target, now());
+            assert_eq!(at.unwrap().to_rfc3339(), expected, "{target}");
+        }
+    }
+
+    #[test]
+    fn accepts_local_times() {
+        let cases = [
+            ("2026-12-31 23:59:00", "2026-12-31 23:59:00"),
+            ("2026-12-31", "2026-12-31 00:00:00"),
+            ("20261231", "2026-12-31 00:00:00"),
+            ("2026-12-31T23:59 local", "2026-12-31 23:59:00"),
+        ];
+        for (target, expected) in cases {
+            let at = parse_target_at(target, now()).unwrap();
+            let wall = at.naive_local().format("%Y-%m-%d %H:%M:%S").to_string();
+            assert_eq!(wall, expected, "{target}");
+            let local = Local.offset_from_utc_datetime(&at.naive_utc()).fix();
+            assert_eq!(*at.offset(), local, "{target}");
+        }
+    }
+
+    #[test]
+    fn accepts_timestamps() {
+        for target in ["1798732800", "@1798732800", "@0001798732800"] {
+            let at = parse_target_at(target, now()).unwrap();
+            assert_eq!(at.timestamp(), 1_798_732_800, "{target}");
+        }
+        assert_eq!(parse_target_at("@0", now()).unwrap().timestamp(), 0);
+    }
+
+    #[test]
+    fn rejects() {
+        // The offending word and the column it starts at, or `None` when the target
+        // ends too early.
+        let cases = [
+            ("", None),
+            ("   ", None),
+            ("next", None),
+            ("someday", Some(("someday", 1))),
+            ("2026-13-01", Some(("2026-13-01", 1))),
+            ("20261331", Some(("20261331", 1))),
+            ("1234567", Some(("1234567", 1))),
+            ("@", Some(("@", 1))),
+            ("@12ab", Some(("@12ab", 1))),
+            ("next 17:00", Some(("17:00", 6))),
+            ("next week", Some(("week", 6))),
+            ("tomorrow 9", Some(("9", 10))),
+            ("2026-12-31 25:00", Some(("25:00", 12))),
+            ("2026-12-31T23:59+8:0x", Some(("2026-12-31T23:59+8:0x", 1))),
+            ("2026-12-31 23:59 Mars/Olympus", Some(("Mars/Olympus", 18))),
+            ("2026-12-31T23:59+08:00 UTC", Some(("UTC", 24))),
+            ("2026-12-31 23:59 UTC extra", Some(("extra", 22))),
+            ("1798732800 UTC", Some(("UTC", 12))),
+            ("@99999999999999999", Some(("@99999999999999999", 1))),
+            // Columns count characters, not bytes.
+            ("2026-12-31\u{3000}25:00", Some(("25:00", 12))),
+            ("2026-12-31 23:59 时区 UTC", Some(("时区", 18))),
+        ];
+        for (target, expected) in cases {
+            let error = parse_target_at(target, now()).unwrap_err();
+            match (&error, expected) {
+                (TargetParseError::UnexpectedEnd { .. }, None) => {}
+                (
+                    TargetParseError::Unexpected { token, column, .. },
+                    Some((expected_token, expected_column)),
+                ) => assert_eq!(
+                    (token.as_str(), *column),
+                    (expected_token, expected_column),
+                    "{target}"
+                ),
+                _ => panic!("{target}: {error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn target_tells_durations_from_points_in_time() {
+        let at = |target| Target::parse_at(target, now()).unwrap();
+        assert_eq!(at("25m"), Target::After(TimeDelta::minutes(25)));
+        assert_eq!(at("PT90M"), Target::After(TimeDelta::minutes(90)));
+        assert_eq!(
+            at("2026-12-31 UTC"),
+            Target::At(DateTime::parse_from_rfc3339("2026-12-31T00:00:00Z").unwrap())
+        );
+        assert!(matches!(at("20261231"), Target::At(_)));
+        assert!(matches!(at("1798732800"), Target::At(_)));
+
+        for target in ["25x", "PT5", "1mo"] {
+            let error = Target::parse_at(target, now()).unwrap_err();
+            assert!(matches!(error, TargetParseError::Duration(_)), "{target}");
+        }
+        let error = Target::parse_at("2026-12-32", now()).unwrap_err();
+        assert!(matches!(error, TargetParseError::Unexpected { .. }));
+    }
+
+    #[test]
+    fn target_resolves_durations_from_the_start() {
+        let start = DateTime::parse_from_rfc3339("2026-10-18T10:00:00+02:00").unwrap();
+        let after = Target::After(TimeDelta::minutes(25));
+        assert_eq!(
+            after.resolve(start).to_rfc3339(),
+            "2026-10-18T10:25:00+02:00"
+        );
+        let at = Target::At(start + TimeDelta::days(1));
+        assert_eq!(at.resolve(start), start + TimeDelta::days(1));
+    }
+
+    #[test]
+    fn target_zone_returns_the_zone_as_written() {
+        let cases = [
+            ("2026-12-31T23:59:00+08:00", Some("+08:00")),
+            ("2026-12-31T23:59:00z", Some("Z")),
+            ("2026-12-31 Europe/Berlin", Some("Europe/Berlin")),
+            ("next friday 17:00 UTC", Some("UTC")),
+            ("2026-12-31 23:59 local", Some("local")),
+            ("2026-12-31 23:59", None),
+            ("@1798732800", None),
+            ("25m", None),
+            ("2026-12-31 Mars/Olympus", None),
+        ];
+        for (target, zone) in cases {
+            assert_eq!(target_zone(target), zone, "{target}");
+        }
+    }
+}
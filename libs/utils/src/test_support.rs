@@ -0,0 +1,44 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::Clock;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::cell::Cell;
+
+/// A clock the test moves forward by hand.
+pub struct ManualClock(Cell<DateTime<Utc>>);
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self(Cell::new(start))
+    }
+
+    pub fn advance(&self, by: TimeDelta) {
+        self.0.set(self.0.get() + by);
+    }
+}
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0.get()
+    }
+}
+
+/// The instant `timestamp` seconds after the Unix epoch.
+pub fn at(timestamp: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(timestamp, 0).unwrap()
+}
+
+/// A small deterministic generator, so failures can be replayed.
+pub struct Lcg(pub u64);
+impl Lcg {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 11
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
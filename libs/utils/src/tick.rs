@@ -0,0 +1,297 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::Clock;
+use chrono::{DateTime, Utc};
+use glib::thread_guard::ThreadGuard;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Decides which wall-clock boundary to display. Boundaries are the multiples of
+/// `period` since the Unix epoch, so a period of one second ticks on whole seconds.
+#[derive(Debug, Clone)]
+pub struct TickSchedule {
+    period_ms: i64,
+    last: Option<i64>,
+}
+impl TickSchedule {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period_ms: (period.as_millis() as i64).max(1),
+            last: None,
+        }
+    }
+
+    /// The latest boundary at or before `now_ms`.
+    pub fn boundary(&self, now_ms: i64) -> i64 {
+        now_ms.div_euclid(self.period_ms) * self.period_ms
+    }
+
+    /// Time left from `now_ms` until the next boundary.
+    pub fn delay(&self, now_ms: i64) -> Duration {
+        Duration::from_millis((self.period_ms - now_ms.rem_euclid(self.period_ms)) as u64)
+    }
+
+    /// Returns the boundary to display at `now_ms`, or `None` if it was already
+    /// returned, so a timer firing slightly early never shows a value twice.
+    pub fn tick(&mut self, now_ms: i64) -> Option<i64> {
+        let boundary = self.boundary(now_ms);
+        if self.last.is_some_and(|last| boundary <= last) {
+            return None;
+        }
+        self.last = Some(boundary);
+        Some(boundary)
+    }
+}
+
+#[derive(Default)]
+struct TickerState {
+    source: RefCell<Option<glib::Source>>,
+    stopped: Cell<bool>,
+}
+
+/// Calls a function on the GLib main loop at every boundary of a `TickSchedule`.
+/// Each wait is computed afresh from the clock, so ticks never drift. Ticking
+/// stops when the `Ticker` is dropped.
+pub struct Ticker {
+    state: Rc<TickerState>,
+}
+impl Ticker {
+    /// Starts ticking every `period` of `clock`, calling `on_tick` once right away and
+    /// then with the boundary instant each time one is crossed. A clock that stands
+    /// still ticks only once.
+    pub fn start(
+        period: Duration,
+        clock: Rc<dyn Clock>,
+        on_tick: impl FnMut(DateTime<Utc>) + 'static,
+    ) -> Self {
+        let state = Rc::new(TickerState::default());
+        fire(
+            state.clone(),
+            TickerRun {
+                schedule: TickSchedule::new(period),
+                clock,
+                on_tick,
+            },
+        );
+        Self { state }
+    }
+}
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        self.state.stopped.set(true);
+        if let Some(source) = self.state.source.take() {
+            source.destroy();
+        }
+    }
+}
+
+struct TickerRun<F> {
+    schedule: TickSchedule,
+    clock: Rc<dyn Clock>,
+    on_tick: F,
+}
+
+fn fire<F: FnMut(DateTime<Utc>) + 'static>(state: Rc<TickerState>, mut run: TickerRun<F>) {
+    let now_ms = run.clock.now().timestamp_millis();
+    let boundary = run.schedule.tick(now_ms);
+    if let Some(boundary) = boundary.and_then(DateTime::from_timestamp_millis) {
+        (run.on_tick)(boundary);
+    }
+    // The callback may have dropped the ticker.
+    if state.stopped.get() {
+        return;
+    }
+    let rate = run.clock.rate();
+    if rate <= 0.0 {
+        return;
+    }
+    let delay = run.schedule.delay(run.clock.now().timestamp_millis());
+    let delay = Duration::from_secs_f64(delay.as_secs_f64() / rate).max(Duration::from_millis(1));
+    // Attached to the thread's default context rather than the global one, so the
+    // ticker runs on whichever main loop started it. Only that thread calls it.
+    let mut next = ThreadGuard::new(Some((state.clone(), run)));
+    let source = glib::timeout_source_new(delay, None, glib::Priority::DEFAULT, move || {
+        if let Some((state, run)) = next.get_mut().take() {
+            // A one-shot source is gone once it has fired.
+            state.source.take();
+            fire(state, run);
+        }
+        glib::ControlFlow::Break
+    });
+    source.attach(Some(&glib::MainContext::ref_thread_default()));
+    state.source.replace(Some(source));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Lcg;
+    use crate::{FixedClock, OffsetClock, ScaledClock, SystemClock};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn boundaries_and_delays() {
+        let schedule = TickSchedule::new(SECOND);
+        let cases = [
+            (0, 0, 1000),
+            (1, 0, 999),
+            (999, 0, 1),
+            (1000, 1000, 1000),
+            (-1, -1000, 1),
+            (-1000, -1000, 1000),
+        ];
+        for (now, boundary, delay) in cases {
+            assert_eq!(schedule.boundary(now), boundary, "{now}");
+            assert_eq!(schedule.delay(now), Duration::from_millis(delay), "{now}");
+        }
+    }
+
+    #[test]
+    fn early_firing_waits_for_the_boundary() {
+        let mut schedule = TickSchedule::new(SECOND);
+        assert_eq!(schedule.tick(1000), Some(1000));
+        // Woken 2 ms before the next second: nothing new to show yet.
+        assert_eq!(schedule.tick(1998), None);
+        assert_eq!(schedule.delay(1998), Duration::from_millis(2));
+        assert_eq!(schedule.tick(2000), Some(2000));
+        assert_eq!(schedule.tick(2000), None);
+    }
+
+    #[test]
+    fn late_firing_shows_each_boundary_once() {
+        let mut schedule = TickSchedule::new(SECOND);
+        assert_eq!(schedule.tick(1000), Some(1000));
+        assert_eq!(schedule.tick(2400), Some(2000));
+        // The next wait is shortened, so the following second is not late.
+        assert_eq!(schedule.delay(2400), Duration::from_millis(600));
+        assert_eq!(schedule.tick(3000), Some(3000));
+    }
+
+    #[test]
+    fn stalled_loop_jumps_to_the_current_boundary_once() {
+        let mut schedule = TickSchedule::new(SECOND);
+        assert_eq!(schedule.tick(1000), Some(1000));
+        assert_eq!(schedule.tick(4200), Some(4000));
+        assert_eq!(schedule.tick(4999), None);
+        assert_eq!(schedule.tick(5000), Some(5000));
+        // A clock set back never shows an older value again.
+        assert_eq!(schedule.tick(3500), None);
+        assert_eq!(schedule.tick(6000), Some(6000));
+    }
+
+    /// Drives the schedule like `Ticker` does, waking up to a period early or late,
+    /// and checks that every boundary is shown exactly once and in order.
+    #[test]
+    fn jitter_never_skips_or_repeats() {
+        for period_ms in [1, 7, 100, 1000] {
+            let mut schedule = TickSchedule::new(Duration::from_millis(period_ms));
+            let period_ms = period_ms as i64;
+            let mut rng = Lcg(0x2545_f491);
+            let mut now = 123_456;
+            let mut shown = Vec::new();
+            for _ in 0..1000 {
+                shown.extend(schedule.tick(now));
+                let jitter = rng.below(period_ms as u64) as i64;
+                let early = rng.below(2) == 0;
+                let delay = schedule.delay(now).as_millis() as i64;
+                now += if early {
+                    (delay - jitter).max(1)
+                } else {
+                    delay + jitter
+                };
+            }
+            assert!(shown.len() > 100, "period {period_ms}");
+            assert_eq!(shown[0], schedule.boundary(123_456));
+            for pair in shown.windows(2) {
+                assert_eq!(pair[1] - pair[0], period_ms, "period {period_ms}");
+            }
+        }
+    }
+
+    fn at_ms(timestamp_ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(timestamp_ms).unwrap()
+    }
+
+    /// Runs `context`, which must be this thread's default, for `limit` of real time.
+    fn run_for(context: &glib::MainContext, limit: Duration) {
+        let main_loop = glib::MainLoop::new(Some(context), false);
+        let timeout = glib::timeout_source_new(limit, None, glib::Priority::DEFAULT, {
+            let main_loop = main_loop.clone();
+            move || {
+                main_loop.quit();
+                glib::ControlFlow::Break
+            }
+        });
+        timeout.attach(Some(context));
+        main_loop.run();
+    }
+
+    /// Starts a `Ticker` on a private main loop, runs it for `limit` and returns the
+    /// instants it ticked. `stop_after` ticks drop the ticker from its own callback.
+    fn ticks(
+        clock: Rc<dyn Clock>,
+        limit: Duration,
+        stop_after: Option<usize>,
+    ) -> Vec<DateTime<Utc>> {
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let ticks = Rc::new(RefCell::new(Vec::new()));
+                let ticker = Rc::new(RefCell::new(None));
+                let started = Ticker::start(SECOND, clock, {
+                    let ticks = ticks.clone();
+                    let ticker = ticker.clone();
+                    move |now| {
+                        ticks.borrow_mut().push(now);
+                        if Some(ticks.borrow().len()) == stop_after {
+                            ticker.take();
+                        }
+                    }
+                });
+                ticker.replace(Some(started));
+                run_for(&context, limit);
+                ticker.take();
+                ticks.take()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn ticker_shows_each_second_once() {
+        // Ten times as fast, so a second of the clock passes every 100 ms.
+        let clock = Rc::new(ScaledClock::new(
+            OffsetClock::starting_at(SystemClock, at_ms(1_000_400)),
+            10.0,
+        ));
+        let shown = ticks(clock.clone(), Duration::from_millis(450), None);
+        assert_eq!(shown[0], at_ms(1_000_000));
+        assert!(shown.len() >= 3, "{shown:?}");
+        for pair in shown.windows(2) {
+            assert!(pair[0] < pair[1], "{shown:?}");
+        }
+        for tick in &shown {
+            assert_eq!(tick.timestamp_subsec_millis(), 0, "{shown:?}");
+            assert!(*tick <= clock.now());
+        }
+    }
+
+    #[test]
+    fn ticker_on_a_stopped_clock_ticks_once() {
+        let clock = Rc::new(FixedClock(at_ms(1_000_500)));
+        let shown = ticks(clock, Duration::from_millis(50), None);
+        assert_eq!(shown, [at_ms(1_000_000)]);
+    }
+
+    #[test]
+    fn ticker_dropped_by_its_callback_stops() {
+        let clock = Rc::new(ScaledClock::new(
+            OffsetClock::starting_at(SystemClock, at_ms(1_000_400)),
+            10.0,
+        ));
+        let shown = ticks(clock, Duration::from_millis(400), Some(2));
+        assert_eq!(shown.len(), 2, "{shown:?}");
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::InvalidUnitError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A unit the remaining time can be shown in. Serialized as its short name (`ms`, `mo`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeUnit {
+    #[serde(rename = "ms")]
+    Millisecond,
+    #[serde(rename = "s")]
+    Second,
+    #[serde(rename = "m")]
+    Minute,
+    #[serde(rename = "h")]
+    Hour,
+    #[serde(rename = "d")]
+    Day,
+    #[serde(rename = "w")]
+    Week,
+    #[serde(rename = "mo")]
+    Month,
+    #[serde(rename = "y")]
+    Year,
+}
+impl TimeUnit {
+    /// Every unit, smallest first.
+    pub const ALL: [TimeUnit; 8] = [
+        TimeUnit::Millisecond,
+        TimeUnit::Second,
+        TimeUnit::Minute,
+        TimeUnit::Hour,
+        TimeUnit::Day,
+        TimeUnit::Week,
+        TimeUnit::Month,
+        TimeUnit::Year,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeUnit::Millisecond => "ms",
+            TimeUnit::Second => "s",
+            TimeUnit::Minute => "m",
+            TimeUnit::Hour => "h",
+            TimeUnit::Day => "d",
+            TimeUnit::Week => "w",
+            TimeUnit::Month => "mo",
+            TimeUnit::Year => "y",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeUnit::Millisecond => "毫秒",
+            TimeUnit::Second => "秒",
+            TimeUnit::Minute => "分钟",
+            TimeUnit::Hour => "小时",
+            TimeUnit::Day => "日",
+            TimeUnit::Week => "周",
+            TimeUnit::Month => "月",
+            TimeUnit::Year => "年",
+        }
+    }
+
+    /// Fixed length in milliseconds; months are 30 days and years 365 days.
+    pub fn millis(self) -> i64 {
+        match self {
+            TimeUnit::Millisecond => 1,
+            TimeUnit::Second => 1000,
+            TimeUnit::Minute => 1000 * 60,
+            TimeUnit::Hour => 1000 * 60 * 60,
+            TimeUnit::Day => 1000 * 60 * 60 * 24,
+            TimeUnit::Week => 1000 * 60 * 60 * 24 * 7,
+            TimeUnit::Month => 1000 * 60 * 60 * 24 * 30,
+            TimeUnit::Year => 1000 * 60 * 60 * 24 * 365,
+        }
+    }
+
+    /// Length in calendar months for units that follow the calendar.
+    pub fn calendar_months(self) -> Option<i64> {
+        match self {
+            TimeUnit::Month => Some(1),
+            TimeUnit::Year => Some(12),
+            _ => None,
+        }
+    }
+}
+impl FromStr for TimeUnit {
+    type Err = InvalidUnitError;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        TimeUnit::ALL
+            .into_iter()
+            .find(|u| u.as_str() == unit)
+            .ok_or_else(|| InvalidUnitError::new(unit))
+    }
+}
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for unit in TimeUnit::ALL {
+            assert_eq!(unit.to_string(), unit.as_str());
+            assert_eq!(unit.as_str().parse::<TimeUnit>().unwrap(), unit);
+            let yaml = serde_yaml::to_string(&unit).unwrap();
+            assert_eq!(yaml.trim(), unit.as_str());
+            assert_eq!(serde_yaml::from_str::<TimeUnit>(&yaml).unwrap(), unit);
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        for unit in ["", "M", "sec", "months"] {
+            assert!(unit.parse::<TimeUnit>().is_err(), "{unit}");
+            assert!(serde_yaml::from_str::<TimeUnit>(unit).is_err(), "{unit}");
+        }
+        assert!(" mo".parse::<TimeUnit>().is_err());
+    }
+
+    #[test]
+    fn all_is_sorted_by_length() {
+        assert!(
+            TimeUnit::ALL
+                .windows(2)
+                .all(|w| w[0].millis() < w[1].millis())
+        );
+    }
+}
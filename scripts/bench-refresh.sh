@@ -0,0 +1,49 @@
+#!/bin/sh
+# Copyright (C) 2024 CaSilicate
+# SPDX-License-Identifier: GPL-3.0-or-later
+
+# Measures how much CPU a chronotimer binary uses while it shows a countdown.
+#
+#   scripts/bench-refresh.sh BINARY [OPTION]...
+#
+# Runs BINARY with a fresh default config and the given options for
+# $BENCH_SECONDS seconds (10 by default) and prints its CPU use, averaged
+# over the run, as a percentage of one core. It needs a display.
+#
+# To compare the refresh modes with the old thread-and-channel loop, build the
+# commit before the ticker was introduced (8aa9999) into another directory and
+# run both binaries with the same options:
+#
+#   scripts/bench-refresh.sh ./target/release/chronotimer --interval 1 --refresh-mode interval
+#   scripts/bench-refresh.sh ./target/release/chronotimer --interval 1 --refresh-mode frame
+#   scripts/bench-refresh.sh ../old/target/release/chronotimer --interval 1
+
+set -eu
+
+if [ $# -lt 1 ]; then
+    sed -n '5,19s/^# \{0,1\}//p' "$0" >&2
+    exit 2
+fi
+binary=$1
+shift
+seconds=${BENCH_SECONDS:-10}
+
+dir=$(mktemp -d)
+trap 'rm -rf "$dir"' EXIT
+
+"$binary" --config "$dir/config.yaml" "$@" >"$dir/output.log" 2>&1 &
+pid=$!
+# Leave the window a second to start before measuring.
+sleep 1
+if ! kill -0 "$pid" 2>/dev/null; then
+    cat "$dir/output.log" >&2
+    exit 1
+fi
+start=$(awk '{ print $14 + $15 }' "/proc/$pid/stat")
+sleep "$seconds"
+end=$(awk '{ print $14 + $15 }' "/proc/$pid/stat")
+kill "$pid"
+wait "$pid" 2>/dev/null || true
+
+awk -v ticks=$((end - start)) -v hz="$(getconf CLK_TCK)" -v seconds="$seconds" \
+    'BEGIN { printf "CPU: %.1f%%\n", 100 * ticks / hz / seconds }'
@@ -0,0 +1,267 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use clap::Parser;
+use config::{
+    ConfigFile, Direction, DisplayMode, ExportFormat, FinishAction, RefreshMode, Transition,
+};
+use std::path::PathBuf;
+use std::rc::Rc;
+use utils::{Clock, OffsetClock, Rounding, ScaledClock, SystemClock, TimeUnit};
+
+/// Command-line options. Every setting outside the `timers`, `hooks`,
+/// `notifications` and `sounds.milestones` lists can be overridden here; anything
+/// left out keeps the value from the config file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to use instead of the default lookup
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Print the effective config, after overrides, and exit
+    #[arg(long)]
+    pub print_config: bool,
+    /// Pretend the current time is this, e.g. "2025-11-21 23:59:50"
+    #[arg(long, value_name = "TIME")]
+    pub fake_now: Option<String>,
+    /// Run the clock this many times as fast
+    #[arg(long)]
+    pub speed: Option<f64>,
+
+    /// Target time, e.g. "2025-11-22 00:00:00 Europe/Berlin", "2026-12-31T23:59+08:00"
+    /// or "next friday 17:00", or a duration from launch, e.g. "25m" or "PT90M"
+    #[arg(long)]
+    pub target: Option<String>,
+    /// Resume duration targets from the last run instead of starting over
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub resume: Option<bool>,
+    /// down, up (time since the target) or auto (down, then up once reached)
+    #[arg(long)]
+    pub direction: Option<Direction>,
+    /// Refresh interval in milliseconds
+    #[arg(long)]
+    pub interval: Option<i32>,
+    /// Number of decimal places
+    #[arg(long, allow_negative_numbers = true)]
+    pub precision: Option<i32>,
+    /// floor, ceil or nearest
+    #[arg(long)]
+    pub rounding: Option<Rounding>,
+    #[arg(long)]
+    pub header: Option<String>,
+    #[arg(long)]
+    pub footer: Option<String>,
+    #[arg(long)]
+    pub header_fontsize: Option<i32>,
+    #[arg(long)]
+    pub time_fontsize: Option<i32>,
+    #[arg(long)]
+    pub footer_fontsize: Option<i32>,
+    #[arg(long)]
+    pub window_title: Option<String>,
+    #[arg(long)]
+    pub window_width: Option<i32>,
+    #[arg(long)]
+    pub window_height: Option<i32>,
+    /// One of ms, s, m, h, d, w, mo, y
+    #[arg(long)]
+    pub unit: Option<TimeUnit>,
+    /// Count months and years on the real calendar
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub calendar_units: Option<bool>,
+    /// scalar or breakdown
+    #[arg(long)]
+    pub display_mode: Option<DisplayMode>,
+    /// Template for breakdown mode, e.g. "{d}d {h:02}:{m:02}:{s:02}"
+    #[arg(long)]
+    pub breakdown_format: Option<String>,
+    /// interval, or frame to redraw once per displayed frame
+    #[arg(long)]
+    pub refresh_mode: Option<RefreshMode>,
+
+    /// continue, stop, overdue, message or close once the target is reached
+    #[arg(long)]
+    pub on_finish: Option<FinishAction>,
+    /// Prefix for the overdue time
+    #[arg(long)]
+    pub overdue_prefix: Option<String>,
+    /// Message shown once the target is reached
+    #[arg(long)]
+    pub finish_message: Option<String>,
+    /// Flash the window once the target is reached
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub flash: Option<bool>,
+
+    /// Sound file played when a countdown reaches its target
+    #[arg(long, value_name = "FILE")]
+    pub finish_sound: Option<String>,
+    /// Sound file played on each of the last --tick-seconds seconds
+    #[arg(long, value_name = "FILE")]
+    pub tick_sound: Option<String>,
+    /// Number of seconds before the target that play the tick sound
+    #[arg(long)]
+    pub tick_seconds: Option<i64>,
+    /// Sound volume from 0 to 1
+    #[arg(long)]
+    pub volume: Option<f64>,
+    /// GStreamer audio sink for sounds, e.g. fakesink to play nothing
+    #[arg(long)]
+    pub audio_sink: Option<String>,
+
+    /// Show one timer at a time, rotating through all of them
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub carousel: Option<bool>,
+    /// Seconds each timer stays on screen in the carousel
+    #[arg(long)]
+    pub page_seconds: Option<i32>,
+    /// cut or crossfade
+    #[arg(long)]
+    pub transition: Option<Transition>,
+    /// Length of the carousel transition in milliseconds
+    #[arg(long)]
+    pub transition_duration: Option<i32>,
+
+    /// Show a stopwatch instead of a countdown
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub stopwatch: Option<bool>,
+    /// Number of laps listed under the stopwatch
+    #[arg(long)]
+    pub laps_shown: Option<i32>,
+    /// csv or json
+    #[arg(long)]
+    pub export_format: Option<ExportFormat>,
+    /// Directory stopwatch laps are exported to
+    #[arg(long, value_name = "DIR")]
+    pub export_dir: Option<String>,
+
+    /// Show the wall clock instead of a countdown
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub clock: Option<bool>,
+    /// Show the clock fullscreen
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub fullscreen: Option<bool>,
+    /// Show seconds on the clock
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub show_second: Option<bool>,
+    /// Font size of the clock
+    #[arg(long)]
+    pub clock_fontsize: Option<i32>,
+}
+impl Cli {
+    /// The clock to display, as set by `--fake-now` and `--speed`.
+    pub fn clock(&self) -> Result<Rc<dyn Clock>, String> {
+        let start = match &self.fake_now {
+            Some(fake_now) => Some(utils::parse_target(fake_now).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        if let Some(speed) = self.speed.filter(|speed| speed.is_nan() || *speed < 0.0) {
+            return Err(format!(
+                "--speed must be a non-negative number, got {}",
+                speed
+            ));
+        }
+        let clock: Rc<dyn Clock> = match (start, self.speed) {
+            (None, None) => Rc::new(SystemClock),
+            (Some(start), None) => Rc::new(OffsetClock::starting_at(SystemClock, start.to_utc())),
+            (None, Some(speed)) => Rc::new(ScaledClock::new(SystemClock, speed)),
+            (Some(start), Some(speed)) => Rc::new(ScaledClock::new(
+                OffsetClock::starting_at(SystemClock, start.to_utc()),
+                speed,
+            )),
+        };
+        Ok(clock)
+    }
+
+    pub fn apply(&self, config: &mut ConfigFile) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut config.target, &self.target);
+        set(&mut config.resume, &self.resume);
+        set(&mut config.direction, &self.direction);
+        set(&mut config.interval, &self.interval);
+        set(&mut config.precision, &self.precision);
+        set(&mut config.rounding, &self.rounding);
+        set(&mut config.header, &self.header);
+        set(&mut config.footer, &self.footer);
+        set(&mut config.header_fontsize, &self.header_fontsize);
+        set(&mut config.time_fontsize, &self.time_fontsize);
+        set(&mut config.footer_fontsize, &self.footer_fontsize);
+        set(&mut config.window_title, &self.window_title);
+        set(&mut config.window_width, &self.window_width);
+        set(&mut config.window_height, &self.window_height);
+        set(&mut config.unit, &self.unit);
+        set(&mut config.calendar_units, &self.calendar_units);
+        set(&mut config.display_mode, &self.display_mode);
+        set(&mut config.breakdown_format, &self.breakdown_format);
+        set(&mut config.refresh_mode, &self.refresh_mode);
+
+        let on_finish = &mut config.on_finish;
+        set(&mut on_finish.action, &self.on_finish);
+        set(&mut on_finish.overdue_prefix, &self.overdue_prefix);
+        set(&mut on_finish.message, &self.finish_message);
+        set(&mut on_finish.flash, &self.flash);
+
+        let sounds = &mut config.sounds;
+        set(&mut sounds.finish, &self.finish_sound);
+        set(&mut sounds.tick, &self.tick_sound);
+        set(&mut sounds.tick_seconds, &self.tick_seconds);
+        set(&mut sounds.volume, &self.volume);
+        set(&mut sounds.sink, &self.audio_sink);
+
+        let carousel = &mut config.carousel_settings;
+        set(&mut carousel.enable, &self.carousel);
+        set(&mut carousel.page_seconds, &self.page_seconds);
+        set(&mut carousel.transition, &self.transition);
+        set(&mut carousel.transition_duration, &self.transition_duration);
+
+        let stopwatch = &mut config.stopwatch_settings;
+        set(&mut stopwatch.enable, &self.stopwatch);
+        set(&mut stopwatch.laps_shown, &self.laps_shown);
+        set(&mut stopwatch.export_format, &self.export_format);
+        set(&mut stopwatch.export_dir, &self.export_dir);
+
+        let clockmode = &mut config.clockmode_settings;
+        set(&mut clockmode.enable, &self.clock);
+        set(&mut clockmode.fullscreen, &self.fullscreen);
+        set(&mut clockmode.showsecond, &self.show_second);
+        set(&mut clockmode.fontsize, &self.clock_fontsize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn options_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn sound_options_override_the_config() {
+        let cli = Cli::parse_from([
+            "chronotimer",
+            "--finish-sound",
+            "bell.ogg",
+            "--tick-sound",
+            "tick.ogg",
+            "--tick-seconds",
+            "3",
+            "--volume",
+            "0.5",
+        ]);
+        let mut config = ConfigFile::default();
+        config.sounds.sink = "fakesink".to_string();
+        cli.apply(&mut config);
+        assert_eq!(config.sounds.finish, "bell.ogg");
+        assert_eq!(config.sounds.tick, "tick.ogg");
+        assert_eq!(config.sounds.tick_seconds, 3);
+        assert_eq!(config.sounds.volume, 0.5);
+        // Left out, so kept from the file.
+        assert_eq!(config.sounds.sink, "fakesink");
+    }
+}
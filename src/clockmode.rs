@@ -5,21 +5,28 @@ use chrono::Utc;
 use chrono::prelude::*;
 use gtk::prelude::*;
 use gtk::{Label, Window, pango};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
-use std::thread;
+use std::rc::Rc;
 use std::time::Duration;
+use utils::Clock;
 
 pub struct ClockmodeConfig {
     pub fullscreen: bool,
     pub winwidth: i32,
     pub winhet: i32,
-    pub interval: i32,
     pub show_second: bool,
     pub font_size: i32,
 }
 
+/// How often the displayed time changes: every second, or every minute when
+/// seconds are hidden.
+pub fn tick_period(show_second: bool) -> Duration {
+    if show_second {
+        Duration::from_secs(1)
+    } else {
+        Duration::from_secs(60)
+    }
+}
+
 pub fn change_fontsize(label: &Label, fontsize: i32) {
     let attr_list = pango::AttrList::new();
     let fontdesc =
@@ -29,11 +36,10 @@ pub fn change_fontsize(label: &Label, fontsize: i32) {
     label.set_attributes(Some(&attr_list));
 }
 
-pub fn clockmode_main(cmconfig: ClockmodeConfig) {
+pub fn clockmode_main(cmconfig: ClockmodeConfig, clock: Rc<dyn Clock>) {
     let fullscreenc = cmconfig.fullscreen;
     let winwidthc = cmconfig.winwidth;
     let winhetc = cmconfig.winhet;
-    let intervalc = cmconfig.interval;
     let show_secondc = cmconfig.show_second;
     let fontsizec = cmconfig.font_size;
 
@@ -51,48 +57,21 @@ pub fn clockmode_main(cmconfig: ClockmodeConfig) {
         mainwindow.set_width_request(winwidthc);
     }
 
-    let exit_flag = Arc::new(AtomicBool::new(false));
-    let exit_flag_c1 = exit_flag.clone();
-    let exit_flag_c2 = exit_flag.clone();
-    let (sender, receiver) = std::sync::mpsc::channel::<String>();
-    glib::timeout_add_local(
-        Duration::from_millis((intervalc as f64 * 0.8) as u64),
-        move || match receiver.try_recv() {
-            Ok(a) => {
-                clocklabel.set_label(a.as_str());
-                glib::ControlFlow::Continue
-            }
-            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-            Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
+    let _ticker = utils::Ticker::start(
+        tick_period(show_secondc),
+        clock,
+        move |now: DateTime<Utc>| {
+            let local = now.with_timezone(&Local);
+            let formatted_time = if show_secondc {
+                local.format("%Y-%m-%d %H:%M:%S").to_string()
+            } else {
+                local.format("%Y-%m-%d %H:%M").to_string()
+            };
+            clocklabel.set_label(formatted_time.as_str());
         },
     );
-    thread::spawn(move || {
-        let looptimer_start = Utc::now().timestamp_millis();
-        let mut repeat_times = 0;
-        loop {
-            let looptimer_current = Utc::now().timestamp_millis();
-            if looptimer_current - (looptimer_start + ((repeat_times * intervalc) as i64)) >= 0 {
-                repeat_times += 1;
-                let local: DateTime<Local> = Local::now();
-                let formatted_time;
-                if show_secondc {
-                    formatted_time = local.format("%Y-%m-%d %H:%M:%S").to_string();
-                } else {
-                    formatted_time = local.format("%Y-%m-%d %H:%M").to_string();
-                }
-                sender.send(formatted_time).unwrap();
-            }
-            if !exit_flag_c1.load(Ordering::Relaxed) {
-                break;
-            }
-            thread::sleep(Duration::from_millis((intervalc as f64 * 0.8) as u64));
-        }
-    });
 
-    mainwindow.connect_destroy(move |_| {
-        exit_flag_c2.store(true, Ordering::Relaxed);
-        gtk::main_quit()
-    });
+    mainwindow.connect_destroy(move |_| gtk::main_quit());
     mainwindow.show_all();
     gtk::main();
 }
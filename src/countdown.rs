@@ -0,0 +1,625 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::clockmode::change_fontsize;
+use crate::hooks::run_hook;
+use crate::notify::{NotificationSender, Notifier, TemplateValues};
+use crate::render::{FLASH_BLINK_LENGTH, FLASH_BLINKS, RowSettings, flash_opacity, should_close};
+use crate::sound::SoundPlayer;
+use chrono::{DateTime, FixedOffset, Local, TimeDelta};
+use config::{
+    ConfigFile, HookConfig, MilestoneSound, RefreshMode, SavedDeadline, SavedDeadlines,
+    SoundConfig, Threshold, TimerSettings, Transition,
+};
+use gtk::gio::Notification;
+use gtk::prelude::*;
+use gtk::{
+    Application, Builder, ButtonsType, DialogFlags, Label, MessageDialog, MessageType, Stack,
+    StackTransitionType, TickCallbackId, Window, gdk,
+};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use utils::{Clock, Crossing, LastSeconds, PausableClock, Target, Ticker, TimerState};
+
+/// A countdown as the refresh sees it: its settings, its label and the thresholds
+/// whose crossing triggers something.
+struct RowState {
+    settings: RowSettings,
+    /// Whether it counts a duration, which stands still while the timer is paused.
+    pausable: bool,
+    header: String,
+    footer: String,
+    label: Label,
+    finish: Crossing,
+    hooks: Vec<(HookConfig, Crossing)>,
+    notifier: Notifier,
+    sounds: SoundConfig,
+    player: Rc<SoundPlayer>,
+    milestones: Vec<(MilestoneSound, Crossing)>,
+    last_seconds: LastSeconds,
+}
+impl RowState {
+    fn new(
+        timer: &TimerSettings,
+        target: Result<DateTime<FixedOffset>, String>,
+        config: &ConfigFile,
+        label: Label,
+        player: Rc<SoundPlayer>,
+        pausable: bool,
+    ) -> Self {
+        let crossing = |threshold_millis| Crossing::new(TimeDelta::milliseconds(threshold_millis));
+        Self {
+            settings: RowSettings::from_timer(timer, target, &config.on_finish),
+            pausable,
+            header: timer.header.clone(),
+            footer: timer.footer.clone(),
+            label,
+            finish: Crossing::new(TimeDelta::zero()),
+            hooks: config
+                .hooks
+                .iter()
+                .map(|hook| (hook.clone(), crossing(hook.threshold_millis())))
+                .collect(),
+            notifier: Notifier::new(&config.notifications),
+            sounds: config.sounds.clone(),
+            player,
+            milestones: config
+                .sounds
+                .milestones
+                .iter()
+                .map(|m| (m.clone(), crossing(m.threshold_millis())))
+                .collect(),
+            last_seconds: LastSeconds::new(config.sounds.tick_seconds),
+        }
+    }
+
+    /// Updates the label for `now`, runs the hooks, sends the notifications and plays
+    /// the sounds whose threshold was just crossed, and returns whether the countdown
+    /// finished just now.
+    fn update(&mut self, now: DateTime<FixedOffset>, app: &Application) -> bool {
+        let text = self.settings.render(now).unwrap_or_else(|e| e);
+        if self.label.text() != text {
+            self.label.set_text(text.as_str());
+        }
+        let (Some(remaining), Ok(target)) = (self.settings.remaining(now), &self.settings.target)
+        else {
+            return false;
+        };
+        let target = *target;
+        for (hook, crossing) in &mut self.hooks {
+            if crossing.update(remaining) {
+                run_hook(
+                    hook,
+                    vec![
+                        ("CHRONOTIMER_TARGET", target.to_rfc3339()),
+                        ("CHRONOTIMER_HEADER", self.header.clone()),
+                        (
+                            "CHRONOTIMER_REMAINING_MS",
+                            remaining.num_milliseconds().to_string(),
+                        ),
+                        (
+                            "CHRONOTIMER_THRESHOLD_MS",
+                            hook.threshold_millis().to_string(),
+                        ),
+                    ],
+                );
+            }
+        }
+        let values = TemplateValues {
+            header: self.header.as_str(),
+            footer: self.footer.as_str(),
+            remaining: text.as_str(),
+            target,
+        };
+        self.notifier.update(remaining, &values, app);
+        for (milestone, crossing) in &mut self.milestones {
+            if crossing.update(remaining) {
+                self.player.play(milestone.file.as_str());
+            }
+        }
+        if self.last_seconds.update(remaining) {
+            self.player.play(self.sounds.tick.as_str());
+        }
+        let finished = self.finish.update(remaining);
+        if finished {
+            self.player.play(self.sounds.finish.as_str());
+        }
+        finished
+    }
+}
+
+impl NotificationSender for Application {
+    fn send(&self, title: &str, body: &str) {
+        let message = Notification::new(title);
+        message.set_body(Some(body));
+        self.send_notification(None, &message);
+    }
+}
+
+/// The header, time and footer labels of one countdown, built from `ui/timer.glade`.
+struct TimerRow {
+    container: gtk::Box,
+    header: Label,
+    time: Label,
+    footer: Label,
+}
+impl TimerRow {
+    fn new() -> Self {
+        let glade_src = include_str!("../ui/timer.glade");
+        let builder = Builder::new();
+        builder.add_from_string(glade_src).unwrap();
+        Self {
+            container: builder.object("timer_box").unwrap(),
+            header: builder.object("l1").unwrap(),
+            time: builder.object("l2").unwrap(),
+            footer: builder.object("l3").unwrap(),
+        }
+    }
+}
+
+/// Keeps the time labels up to date.
+enum Refresh {
+    Interval(Ticker),
+    Frame(TickCallbackId),
+}
+impl Refresh {
+    fn stop(self) {
+        match self {
+            Refresh::Interval(ticker) => drop(ticker),
+            Refresh::Frame(callback) => callback.remove(),
+        }
+    }
+}
+
+/// Where a duration target ends on the countdown's clock.
+#[derive(Clone)]
+struct Deadline {
+    /// The target it was resolved from, e.g. `25m`.
+    target: String,
+    at: DateTime<FixedOffset>,
+}
+
+pub struct Countdown {
+    app: Application,
+    main_window: Window,
+    /// Paused and reset from the keyboard. Duration targets read their time from here,
+    /// the others from its inner clock.
+    clock: Rc<PausableClock<Rc<dyn Clock>>>,
+    title: RefCell<String>,
+    rows_box: gtk::Box,
+    pages: Stack,
+    rows: RefCell<Vec<TimerRow>>,
+    /// Whether `rows` currently live in `pages` rather than `rows_box`.
+    carousel: Cell<bool>,
+    page_timer: RefCell<Option<glib::SourceId>>,
+    refresh: RefCell<Option<Refresh>>,
+    refresh_mode: Cell<RefreshMode>,
+    interval: Cell<Duration>,
+    update: RefCell<Rc<dyn Fn(DateTime<FixedOffset>)>>,
+    /// The config last applied, to rebuild the countdowns on reset.
+    config: RefCell<Option<ConfigFile>>,
+    /// Deadlines of the duration targets, in the order of the timers.
+    deadlines: RefCell<Vec<Option<Deadline>>>,
+    deadlines_path: PathBuf,
+    /// Where relative sound paths are taken from.
+    config_dir: PathBuf,
+    resume: Cell<bool>,
+}
+impl Countdown {
+    /// Creates the window for `config`, loaded from `config_path`. With `config.resume`
+    /// set, duration targets continue from the deadlines saved next to it that have not
+    /// passed yet.
+    pub fn new(
+        app: &Application,
+        config: &ConfigFile,
+        clock: Rc<dyn Clock>,
+        config_path: &Path,
+    ) -> Rc<Self> {
+        let glade_src = include_str!("../ui/main.glade");
+        let builder = Builder::new();
+        builder.add_from_string(glade_src).unwrap();
+        let countdown = Rc::new(Self {
+            app: app.clone(),
+            main_window: builder.object("main_window").unwrap(),
+            clock: Rc::new(PausableClock::new(clock)),
+            title: RefCell::new(String::new()),
+            rows_box: builder.object("rows").unwrap(),
+            pages: builder.object("pages").unwrap(),
+            rows: RefCell::new(Vec::new()),
+            carousel: Cell::new(false),
+            page_timer: RefCell::new(None),
+            refresh: RefCell::new(None),
+            refresh_mode: Cell::new(RefreshMode::Interval),
+            interval: Cell::new(Duration::ZERO),
+            update: RefCell::new(Rc::new(|_| {})),
+            config: RefCell::new(None),
+            deadlines: RefCell::new(Vec::new()),
+            deadlines_path: SavedDeadlines::path(config_path),
+            config_dir: config_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            resume: Cell::new(config.resume),
+        });
+        if config.resume {
+            countdown.load_deadlines(config);
+        }
+
+        // Keep the time spent paused, and whether it still is, for the next run.
+        countdown.main_window.connect_destroy({
+            let countdown = countdown.clone();
+            move |_| {
+                if let Some(refresh) = countdown.refresh.take() {
+                    refresh.stop();
+                }
+                countdown.save_deadlines();
+            }
+        });
+        app.connect_shutdown({
+            let countdown = countdown.clone();
+            move |_| countdown.save_deadlines()
+        });
+        countdown.main_window.set_application(Some(app));
+        countdown.main_window.set_resizable(false);
+        countdown.main_window.connect_focus_in_event(|window, _| {
+            window.set_urgency_hint(false);
+            gtk::glib::Propagation::Proceed
+        });
+        countdown.main_window.connect_key_press_event({
+            let countdown = countdown.clone();
+            move |_, event| {
+                let key = event.keyval().to_lower();
+                if key == gdk::keys::constants::space {
+                    countdown.toggle_pause();
+                } else if key == gdk::keys::constants::r {
+                    countdown.reset();
+                } else {
+                    return gtk::glib::Propagation::Proceed;
+                }
+                gtk::glib::Propagation::Stop
+            }
+        });
+        countdown.rows_box.set_no_show_all(true);
+        countdown.pages.set_no_show_all(true);
+        countdown.apply_config(config);
+        countdown
+    }
+
+    /// Applies `config` to the running window, restarting the refresh.
+    pub fn apply_config(&self, config: &ConfigFile) {
+        self.config.replace(Some(config.clone()));
+        self.resume.set(config.resume);
+        self.title.replace(config.window_title.clone());
+        self.main_window
+            .set_size_request(config.window_width, config.window_height);
+
+        let timers = config.timer_settings();
+        let carousel = &config.carousel_settings;
+        let mut rows = self.rows.borrow_mut();
+        if rows.len() != timers.len() || self.carousel.get() != carousel.enable {
+            let container: &gtk::Container = if self.carousel.get() {
+                self.pages.upcast_ref()
+            } else {
+                self.rows_box.upcast_ref()
+            };
+            for row in rows.drain(..) {
+                container.remove(&row.container);
+            }
+            for _ in &timers {
+                let row = TimerRow::new();
+                if carousel.enable {
+                    self.pages.add(&row.container);
+                } else {
+                    self.rows_box.pack_start(&row.container, false, true, 0);
+                }
+                rows.push(row);
+            }
+            self.carousel.set(carousel.enable);
+        }
+        self.rows_box.set_visible(!carousel.enable);
+        self.pages.set_visible(carousel.enable);
+        for (row, timer) in rows.iter().zip(&timers) {
+            row.header.set_text(timer.header.as_str());
+            row.footer.set_text(timer.footer.as_str());
+            change_fontsize(&row.header, config.header_fontsize);
+            change_fontsize(&row.time, config.time_fontsize);
+            change_fontsize(&row.footer, config.footer_fontsize);
+        }
+
+        self.pages.set_transition_type(match carousel.transition {
+            Transition::Cut => StackTransitionType::None,
+            Transition::Crossfade => StackTransitionType::Crossfade,
+        });
+        self.pages
+            .set_transition_duration(carousel.transition_duration as u32);
+        if let Some(page_timer) = self.page_timer.take() {
+            page_timer.remove();
+        }
+        if carousel.enable && rows.len() > 1 {
+            let pages = self.pages.clone();
+            let page_timer = glib::timeout_add_local(
+                Duration::from_secs(carousel.page_seconds as u64),
+                move || {
+                    show_next_page(&pages);
+                    glib::ControlFlow::Continue
+                },
+            );
+            self.page_timer.replace(Some(page_timer));
+        }
+
+        let on_finish = config.on_finish.clone();
+        let targets = self.resolve_targets(&timers);
+        self.save_deadlines();
+        let player = Rc::new(SoundPlayer::new(&config.sounds, &self.config_dir));
+        let states: Vec<RowState> = rows
+            .iter()
+            .zip(&timers)
+            .zip(targets)
+            .zip(self.deadlines.borrow().iter())
+            .map(|(((row, timer), target), deadline)| {
+                let label = row.time.clone();
+                RowState::new(
+                    timer,
+                    target,
+                    config,
+                    label,
+                    player.clone(),
+                    deadline.is_some(),
+                )
+            })
+            .collect();
+        let states = RefCell::new(states);
+        let window = self.main_window.clone();
+        let app = self.app.clone();
+        let clock = self.clock.clone();
+        let update = move |now: DateTime<FixedOffset>| {
+            let timer_now = clock.time_at(now.to_utc()).with_timezone(&now.timezone());
+            let row_now = |state: &RowState| if state.pausable { timer_now } else { now };
+            let mut states = states.borrow_mut();
+            let mut finished = false;
+            for state in states.iter_mut() {
+                if state.update(row_now(state), &app) {
+                    finished = true;
+                    if on_finish.flash {
+                        flash(&window, &state.label);
+                    }
+                }
+            }
+            if should_close(
+                &on_finish,
+                states.iter().map(|s| (&s.settings, row_now(s))),
+                finished,
+            ) {
+                // Closing destroys the refresh that is calling us, so leave it first.
+                let window = window.clone();
+                glib::idle_add_local_once(move || window.close());
+            }
+        };
+        self.update.replace(Rc::new(update));
+        self.refresh_mode.set(config.refresh_mode);
+        self.interval
+            .set(Duration::from_millis(config.interval as u64));
+        drop(rows);
+        self.show_state();
+        self.start_refresh();
+    }
+
+    /// Parses the target of every timer, resolving durations and relative dates like
+    /// `tomorrow 09:00` from when the clock was started or reset. A duration keeps the
+    /// deadline it already has, so reloading the config does not restart it.
+    fn resolve_targets(
+        &self,
+        timers: &[TimerSettings],
+    ) -> Vec<Result<DateTime<FixedOffset>, String>> {
+        let start = self.clock.origin().with_timezone(&Local).fixed_offset();
+        let mut deadlines = self.deadlines.borrow_mut();
+        deadlines.resize(timers.len(), None);
+        timers
+            .iter()
+            .zip(deadlines.iter_mut())
+            .map(|(timer, deadline)| {
+                let target = Target::parse_at(timer.target.as_str(), self.clock.origin())
+                    .map_err(|e| e.to_string())?;
+                if let Target::At(at) = target {
+                    *deadline = None;
+                    return Ok(at);
+                }
+                let at = match deadline {
+                    Some(deadline) if deadline.target == timer.target => deadline.at,
+                    _ => target.resolve(start),
+                };
+                *deadline = Some(Deadline {
+                    target: timer.target.clone(),
+                    at,
+                });
+                Ok(at)
+            })
+            .collect()
+    }
+
+    /// Takes over the deadlines saved for the timers of `config` that are still ahead
+    /// and were saved for the same target, pausing again if they were paused.
+    fn load_deadlines(&self, config: &ConfigFile) {
+        let saved = SavedDeadlines::load(&self.deadlines_path);
+        // Saved on the real clock, like the deadlines.
+        let now = self.clock.inner().now().fixed_offset();
+        let mut paused = false;
+        let deadlines = config
+            .timer_settings()
+            .iter()
+            .enumerate()
+            .map(|(index, timer)| {
+                let (at, state) = saved.resume(index, timer.target.as_str(), now)?;
+                paused |= state == TimerState::Paused;
+                (at > now).then(|| Deadline {
+                    target: timer.target.clone(),
+                    at: at - self.clock.lag(),
+                })
+            })
+            .collect();
+        self.deadlines.replace(deadlines);
+        if paused {
+            self.clock.pause();
+        }
+    }
+
+    /// Saves the deadlines of the duration targets for `resume`, as they stand on the
+    /// real clock, so time spent paused pushes them back, along with whether they are
+    /// paused.
+    fn save_deadlines(&self) {
+        if !self.resume.get() {
+            return;
+        }
+        let now = self.clock.inner().now();
+        let lag = now - self.clock.time_at(now);
+        let state = self.clock.state();
+        let saved = SavedDeadlines {
+            saved_at: Some(now.fixed_offset().to_rfc3339()),
+            timers: self
+                .deadlines
+                .borrow()
+                .iter()
+                .map(|deadline| {
+                    deadline.as_ref().map(|deadline| SavedDeadline {
+                        target: deadline.target.clone(),
+                        deadline: (deadline.at + lag).to_rfc3339(),
+                        state,
+                    })
+                })
+                .collect(),
+        };
+        if let Err(e) = saved.save(&self.deadlines_path) {
+            eprintln!(
+                "Failed to save deadlines to {}: {}",
+                self.deadlines_path.display(),
+                e
+            );
+        }
+    }
+
+    /// Starts updating the time labels from the inner clock, which keeps running while
+    /// paused, replacing any previous refresh.
+    fn start_refresh(&self) {
+        let update = self.update.borrow().clone();
+        let refresh = match self.refresh_mode.get() {
+            RefreshMode::Interval => Refresh::Interval(Ticker::start(
+                self.interval.get(),
+                self.clock.inner().clone(),
+                move |now| update(now.fixed_offset()),
+            )),
+            RefreshMode::Frame => {
+                let clock = self.clock.inner().clone();
+                update(clock.now().fixed_offset());
+                Refresh::Frame(self.main_window.add_tick_callback(move |_, frame_clock| {
+                    update(frame_time(clock.as_ref(), frame_clock));
+                    gtk::glib::ControlFlow::Continue
+                }))
+            }
+        };
+        if let Some(old) = self.refresh.replace(Some(refresh)) {
+            old.stop();
+        }
+    }
+
+    /// Pauses or resumes the countdowns to a duration. Those to a point in time keep
+    /// running, so without any countdown to a duration there is nothing to pause.
+    fn toggle_pause(&self) {
+        if self.clock.state() == TimerState::Running && !self.has_durations() {
+            return;
+        }
+        self.clock.toggle();
+        self.save_deadlines();
+        self.show_state();
+    }
+
+    /// Starts the countdowns to a duration over from now, dropping the time spent
+    /// paused. Those to a point in time are unaffected.
+    fn reset(&self) {
+        self.clock.reset();
+        self.deadlines.borrow_mut().clear();
+        let config = self.config.borrow().clone();
+        if let Some(config) = config {
+            // This also restarts the ticker, which may have shown later boundaries.
+            self.apply_config(&config);
+        }
+    }
+
+    /// Whether any countdown counts a duration, the only kind pausing affects.
+    fn has_durations(&self) -> bool {
+        self.deadlines.borrow().iter().any(Option::is_some)
+    }
+
+    /// Dims the countdowns to a duration and marks the title while they are paused.
+    fn show_state(&self) {
+        let title = self.title.borrow();
+        let (opacity, title) = match self.clock.state() {
+            TimerState::Paused if self.has_durations() => (0.5, format!("{}（已暂停）", title)),
+            _ => (1.0, title.clone()),
+        };
+        for (row, deadline) in self
+            .rows
+            .borrow()
+            .iter()
+            .zip(self.deadlines.borrow().iter())
+        {
+            row.container
+                .set_opacity(if deadline.is_some() { opacity } else { 1.0 });
+        }
+        self.main_window.set_title(title.as_str());
+    }
+
+    /// Shows a warning next to the window without interrupting the countdown.
+    pub fn warn(&self, message: &str) {
+        eprintln!("{}", message);
+        let dialog = MessageDialog::new(
+            Some(&self.main_window),
+            DialogFlags::DESTROY_WITH_PARENT,
+            MessageType::Warning,
+            ButtonsType::Close,
+            message,
+        );
+        dialog.connect_response(|dialog, _| dialog.close());
+        dialog.show_all();
+    }
+
+    pub fn show(&self) {
+        self.main_window.show_all();
+    }
+}
+
+/// Blinks `label` a few times and asks the window manager to draw attention to `window`.
+fn flash(window: &Window, label: &Label) {
+    window.set_urgency_hint(true);
+    let label = label.clone();
+    let mut blinks = 0;
+    glib::timeout_add_local(FLASH_BLINK_LENGTH, move || {
+        blinks += 1;
+        label.set_opacity(flash_opacity(blinks));
+        if blinks < FLASH_BLINKS {
+            glib::ControlFlow::Continue
+        } else {
+            glib::ControlFlow::Break
+        }
+    });
+}
+
+/// Moves the carousel on to the page after the visible one, wrapping around.
+fn show_next_page(pages: &Stack) {
+    let children = pages.children();
+    let current = pages
+        .visible_child()
+        .and_then(|child| children.iter().position(|c| *c == child))
+        .unwrap_or(0);
+    if let Some(next) = children.get((current + 1) % children.len().max(1)) {
+        pages.set_visible_child(next);
+    }
+}
+
+/// The time on `clock` at which the frame being drawn by `frame_clock` started.
+fn frame_time(clock: &dyn Clock, frame_clock: &gdk::FrameClock) -> DateTime<FixedOffset> {
+    let since_frame = gtk::glib::monotonic_time() - frame_clock.frame_time();
+    let since_frame = TimeDelta::microseconds((since_frame as f64 * clock.rate()) as i64);
+    (clock.now() - since_frame).fixed_offset()
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use config::HookConfig;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Runs `hook.command` through the shell on a background thread, with `env` added to
+/// its environment. Its output is logged line by line, and it is killed once it has
+/// run for `hook.timeout` seconds.
+pub fn run_hook(hook: &HookConfig, env: Vec<(&'static str, String)>) {
+    let command = hook.command.clone();
+    let timeout = Duration::from_secs(hook.timeout.max(0) as u64);
+    thread::spawn(move || match run(command.as_str(), env, timeout) {
+        Outcome::Exited(status) if status.success() => {}
+        Outcome::Exited(status) => eprintln!("Hook '{}' failed: {}", command, status),
+        Outcome::TimedOut => eprintln!(
+            "Hook '{}' timed out after {}s, killed it",
+            command,
+            timeout.as_secs()
+        ),
+        Outcome::Error(e) => eprintln!("Failed to run hook '{}': {}", command, e),
+    });
+}
+
+/// How a hook ended.
+#[derive(Debug)]
+enum Outcome {
+    Exited(ExitStatus),
+    /// Still running after the timeout, so it was killed with everything it started.
+    TimedOut,
+    Error(io::Error),
+}
+
+/// Runs `command` and waits for it and for its output to end.
+fn run(command: &str, env: Vec<(&'static str, String)>, timeout: Duration) -> Outcome {
+    let child = shell(command)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return Outcome::Error(e),
+    };
+    let readers = [
+        log_output(child.stdout.take(), command),
+        log_output(child.stderr.take(), command),
+    ];
+
+    let deadline = Instant::now() + timeout;
+    let outcome = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Outcome::Exited(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                if let Err(e) = kill(&mut child) {
+                    eprintln!("Failed to kill hook '{}': {}", command, e);
+                }
+                let _ = child.wait();
+                break Outcome::TimedOut;
+            }
+            Err(e) => break Outcome::Error(e),
+        }
+    };
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    outcome
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut shell = Command::new("sh");
+    // A process group of its own, so that a timeout also reaches what it started.
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+#[cfg(windows)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+/// Kills the shell and every process in its group, which would otherwise keep the
+/// output pipes open.
+#[cfg(not(windows))]
+fn kill(child: &mut Child) -> io::Result<()> {
+    // The group has the id of the shell leading it.
+    let group = -(child.id() as libc::pid_t);
+    if unsafe { libc::kill(group, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Copies everything `pipe` delivers to the log, prefixed with the hook's command.
+fn log_output(pipe: Option<impl Read + Send + 'static>, command: &str) -> Option<JoinHandle<()>> {
+    let pipe = pipe?;
+    let command = command.to_string();
+    Some(thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            eprintln!("[{}] {}", command, line);
+        }
+    }))
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn quick_hooks_exit_with_their_environment() {
+        let env = vec![("CHRONOTIMER_HEADER", "距离新年".to_string())];
+        let outcome = run(r#"echo "$CHRONOTIMER_HEADER""#, env.clone(), TIMEOUT);
+        assert!(matches!(outcome, Outcome::Exited(status) if status.success()));
+        let outcome = run(r#"test "$CHRONOTIMER_HEADER" = 距离新年"#, env, TIMEOUT);
+        assert!(matches!(outcome, Outcome::Exited(status) if status.success()));
+    }
+
+    #[test]
+    fn failing_hooks_report_their_status() {
+        let outcome = run("echo failing >&2; exit 3", Vec::new(), TIMEOUT);
+        assert!(matches!(outcome, Outcome::Exited(status) if status.code() == Some(3)));
+    }
+
+    #[test]
+    fn hanging_hooks_are_killed_with_their_children() {
+        let started = Instant::now();
+        // The background sleep keeps the output pipes open unless it is killed too.
+        let outcome = run(
+            "sleep 30 & echo started; sleep 30",
+            Vec::new(),
+            Duration::from_millis(200),
+        );
+        assert!(matches!(outcome, Outcome::TimedOut), "{outcome:?}");
+        assert!(started.elapsed() < TIMEOUT, "{:?}", started.elapsed());
+    }
+}
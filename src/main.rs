@@ -3,215 +3,161 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cli;
 mod clockmode;
-
-use chrono::Utc;
-use gtk::prelude::BuilderExtManual;
-use gtk::traits::{GtkWindowExt, LabelExt, WidgetExt};
-use gtk::{Builder, Label, Window};
-use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::Write;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ConfigFile {
-    target: String,
-    interval: i32,
-    precision: i32,
-    header: String,
-    footer: String,
-    header_fontsize: i32,
-    time_fontsize: i32,
-    footer_fontsize: i32,
-    window_title: String,
-    window_width: i32,
-    window_height: i32,
-    unit: String,
-
-    clockmode_settings: ClockmodeConfigConfigfile,
+mod countdown;
+mod hooks;
+mod notify;
+mod render;
+mod sound;
+mod stopwatch;
+
+use clap::Parser;
+use cli::Cli;
+use config::{ConfigError, ConfigFile};
+use countdown::Countdown;
+use gio::prelude::*;
+use gtk::gio::prelude::{ApplicationExt as _, ApplicationExtManual as _};
+use gtk::traits::{DialogExt, GtkWindowExt};
+use gtk::{Application, ButtonsType, DialogFlags, MessageDialog, MessageType, Window};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use utils::Clock;
+
+const APP_ID: &str = "io.github.casilicate_dev.chronotimer";
+
+/// Reports a config problem on stderr and in an error dialog, then exits.
+fn config_error(message: String) -> ! {
+    eprintln!("{}", message);
+    if gtk::init().is_ok() {
+        let dialog = MessageDialog::new(
+            None::<&Window>,
+            DialogFlags::MODAL,
+            MessageType::Error,
+            ButtonsType::Close,
+            message.as_str(),
+        );
+        dialog.set_title("ChronoTimer");
+        dialog.run();
+    }
+    std::process::exit(1);
 }
-#[derive(Debug, Serialize, Deserialize)]
-struct ClockmodeConfigConfigfile {
-    enable: bool,
-    fullscreen: bool,
-    showsecond: bool,
-    fontsize: i32,
+/// Loads the config again for a hot reload, keeping the command-line overrides.
+fn reload_config(path: &Path, cli: &Cli) -> Result<ConfigFile, ConfigError> {
+    let mut config = ConfigFile::load(path)?;
+    cli.apply(&mut config);
+    config.validate()?;
+    Ok(config)
 }
-impl Default for ClockmodeConfigConfigfile {
-    fn default() -> Self {
-        Self {
-            enable: false,
-            fullscreen: false,
-            showsecond: true,
-            fontsize: 100,
+/// Calls `on_change` whenever the file at `path` has been rewritten. The returned
+/// monitor must be kept alive for as long as changes should be reported.
+fn watch_file(path: &Path, on_change: impl Fn() + 'static) -> Option<gio::FileMonitor> {
+    let monitor = gio::File::for_path(path)
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .inspect_err(|e| eprintln!("Failed to watch config file: {}", e))
+        .ok()?;
+    monitor.connect_changed(move |_, _, _, event| {
+        if matches!(
+            event,
+            gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created
+        ) {
+            on_change();
         }
-    }
+    });
+    Some(monitor)
 }
-impl Default for ConfigFile {
-    fn default() -> Self {
-        ConfigFile {
-            target: "2025-11-22 00:00:00".to_string(),
-            interval: 100,
-            precision: 5,
-            header: "Header".to_string(),
-            footer: "Footer".to_string(),
-            header_fontsize: 50,
-            time_fontsize: 50,
-            footer_fontsize: 50,
-            window_title: "ChronoTimer".to_string(),
-            window_width: 200,
-            window_height: 250,
-            unit: "d".to_string(),
-            clockmode_settings: ClockmodeConfigConfigfile::default(),
-        }
-    }
+/// Runs the countdown window as a `gtk::Application`, which desktop notifications need.
+fn run_countdown(config: ConfigFile, config_path: PathBuf, cli: Cli, clock: Rc<dyn Clock>) {
+    let app = Application::new(Some(APP_ID), gtk::gio::ApplicationFlags::NON_UNIQUE);
+    let cli = Rc::new(cli);
+    let monitor = RefCell::new(None);
+    app.connect_activate(move |app| {
+        let countdown = Countdown::new(app, &config, clock.clone(), &config_path);
+        monitor.replace(watch_file(&config_path, {
+            let countdown = countdown.clone();
+            let config_path = config_path.clone();
+            let cli = cli.clone();
+            move || match reload_config(&config_path, &cli) {
+                Ok(config) => {
+                    if config.clockmode_settings.enable {
+                        countdown.warn("Restart the timer to switch to clock mode.");
+                    } else if config.stopwatch_settings.enable {
+                        countdown.warn("Restart the timer to switch to the stopwatch.");
+                    }
+                    countdown.apply_config(&config);
+                }
+                Err(e) => countdown.warn(&format!("Failed to reload config file:\n{}", e)),
+            }
+        }));
+        countdown.show();
+    });
+    // The options are ours; GApplication would reject the ones it does not know.
+    let program: Vec<String> = std::env::args().take(1).collect();
+    app.run_with_args(&program);
 }
 fn main() {
-    let file_content = std::fs::read_to_string("config.yaml").unwrap_or_else(|e| {
-        eprintln!("Failed to read config file: {}", e);
-        let config_init = ConfigFile::default();
-        let config_str = serde_yaml::to_string(&config_init).unwrap();
-        let mut file = File::create("config.yaml").unwrap_or_else(|e| {
-            eprintln!("Failed to create config file: {}", e);
-            std::process::exit(1);
-        });
-        file.write_all(config_str.as_bytes()).unwrap_or_else(|e| {
+    let cli = Cli::parse();
+    let config_path = config::find_config_file(cli.config.clone());
+    // --print-config only reads the file; the upgrade happens in memory on load.
+    if !cli.print_config {
+        match config::upgrade_file(&config_path) {
+            Ok(Some(backup)) => eprintln!(
+                "Upgraded config file, the original was kept as {}",
+                backup.display()
+            ),
+            Ok(None) => {}
+            // A missing file is created with the defaults below.
+            Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!(
+                "Failed to upgrade config file {}: {}",
+                config_path.display(),
+                e
+            ),
+        }
+    }
+    // --print-config never creates the file either.
+    let mut config = match ConfigFile::load_or_default(&config_path, cli.print_config) {
+        Ok((config, None)) => config,
+        Ok((config, Some(e))) => {
+            eprintln!(
+                "Failed to read config file {}: {}",
+                config_path.display(),
+                e
+            );
+            config
+        }
+        Err(ConfigError::Io(e)) => {
             eprintln!("Failed to write config file: {}", e);
             std::process::exit(1);
-        });
-        fs::read_to_string("config.yaml").unwrap_or_else(|e| {
-            eprintln!("Failed to read config file: {}", e);
-            std::process::exit(1);
-        })
-    });
-
-    let config: ConfigFile = serde_yaml::from_str(file_content.as_str()).unwrap_or_else(|e| {
-        eprintln!("Failed to parse config file: {}", e);
-        std::process::exit(1);
-    });
-
-    let target = config.target;
-    let interval = config.interval;
-    let precision = config.precision;
-
-    let header = config.header;
-    let footer = config.footer;
-
-    let header_fontsize = config.header_fontsize;
-    let time_fontsize = config.time_fontsize;
-    let footer_fontsize = config.footer_fontsize;
-
-    let window_title = config.window_title;
-    let window_width = config.window_width;
-    let window_height = config.window_height;
-    let unit = config.unit;
-
-    let clockmode = config.clockmode_settings;
-    let fullscreen = clockmode.fullscreen;
-    let enable_clockmode = clockmode.enable;
-    let show_second = clockmode.showsecond;
-    let clock_fontsize = clockmode.fontsize;
-
-    if enable_clockmode == false {
-        gtk::init().unwrap();
-
-        let glade_src = include_str!("../ui/main.glade");
-        let builder = Builder::new();
-        builder.add_from_string(glade_src).unwrap();
-        let main_window: Window = builder.object("main_window").unwrap();
-        let label1: Label = builder.object("l1").unwrap();
-        let label2: Label = builder.object("l2").unwrap();
-        let label3: Label = builder.object("l3").unwrap();
-
-        let exit_flag = Arc::new(AtomicBool::new(false));
-        let exit_flag_clone = exit_flag.clone();
-        let exit_flag_clone2 = exit_flag.clone();
-        let thread_exit_flag_clone = exit_flag.clone();
-
-        let label2_clone = label2.clone();
-
-        let (sender, receiver) = std::sync::mpsc::channel::<String>();
-        glib::timeout_add_local(
-            Duration::from_millis((interval as f64 * 0.8) as u64),
-            move || match receiver.try_recv() {
-                Ok(a) => {
-                    label2_clone.set_text(a.as_str());
-                    glib::ControlFlow::Continue
-                }
-                Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                Err(mpsc::TryRecvError::Disconnected) => glib::ControlFlow::Break,
-            },
-        );
-
-        label1.set_text(header.as_str());
-        label3.set_text(footer.as_str());
-        main_window.set_title(window_title.as_str());
-        main_window.set_size_request(window_width, window_height);
-        clockmode::change_fontsize(&label1, header_fontsize);
-        clockmode::change_fontsize(&label2, time_fontsize);
-        clockmode::change_fontsize(&label3, footer_fontsize);
-
-        thread::spawn(move || {
-            let target_clone = target.clone();
-            let looptimer_start = Utc::now().timestamp_millis();
-            let mut repeat_times = 0;
-            loop {
-                let target_clone2 = target_clone.clone();
-                let looptimer_current = Utc::now().timestamp_millis();
-                if looptimer_current - (looptimer_start + ((repeat_times * interval) as i64)) >= 0 {
-                    repeat_times += 1;
-                    let target_timestamp =
-                        utils::convert_timestamp(target_clone2).unwrap_or_else(|e| {
-                            sender
-                                .send(format!("Failed to parse target time: {}", e))
-                                .unwrap();
-                            exit_flag_clone.store(true, Ordering::Relaxed);
-                            -1
-                        });
-                    let current_timestamp = Utc::now().timestamp_millis();
-                    let delta = (target_timestamp - current_timestamp) as f64;
-                    let remaining =
-                        utils::convert_time_unit(delta, unit.as_str()).unwrap_or_else(|e| {
-                            sender.send(format!("{}", e)).unwrap();
-                            exit_flag_clone.store(true, std::sync::atomic::Ordering::Relaxed);
-                            -1.0
-                        });
-                    let rounded_remaining = utils::advanced_round(remaining, precision);
-                    let formated_delta = utils::format_zeros(rounded_remaining, precision);
-                    if !thread_exit_flag_clone.load(std::sync::atomic::Ordering::Relaxed) {
-                        sender.send(formated_delta).unwrap();
-                    }
-                }
-                if thread_exit_flag_clone.load(Ordering::Relaxed) {
-                    break;
-                }
-                thread::sleep(Duration::from_millis((interval as f64 * 0.8) as u64));
-            }
-        });
-
-        main_window.connect_destroy(move |_| {
-            exit_flag_clone2.store(true, Ordering::Relaxed);
-            gtk::main_quit()
-        });
-
-        main_window.set_resizable(false);
+        }
+        Err(e) => config_error(format!("Failed to parse config file:\n{}", e)),
+    };
+    cli.apply(&mut config);
+    config
+        .validate()
+        .unwrap_or_else(|e| config_error(format!("Invalid command-line options:\n{}", e)));
+    if cli.print_config {
+        print!("{}", config.to_yaml().unwrap());
+        return;
+    }
+    let clock = cli
+        .clock()
+        .unwrap_or_else(|e| config_error(format!("Invalid command-line options:\n{}", e)));
 
-        main_window.show_all();
-        gtk::main();
-    } else {
+    if config.clockmode_settings.enable {
+        let clockmode = config.clockmode_settings;
         let c = clockmode::ClockmodeConfig {
-            fullscreen,
-            winwidth: window_width,
-            winhet: window_height,
-            interval,
-            show_second,
-            font_size: clock_fontsize,
+            fullscreen: clockmode.fullscreen,
+            winwidth: config.window_width,
+            winhet: config.window_height,
+            show_second: clockmode.showsecond,
+            font_size: clockmode.fontsize,
         };
-        clockmode::clockmode_main(c);
+        clockmode::clockmode_main(c, clock);
+    } else if config.stopwatch_settings.enable {
+        stopwatch::stopwatch_main(&config, clock);
+    } else {
+        run_countdown(config, config_path, cli, clock);
     }
 }
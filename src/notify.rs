@@ -0,0 +1,200 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use config::{NotificationConfig, Threshold};
+use utils::Crossing;
+
+/// Shows a notification. The application sends desktop notifications through it.
+pub trait NotificationSender {
+    fn send(&self, title: &str, body: &str);
+}
+
+/// The notifications of one countdown, each sent when its remaining time drops to
+/// the notification's threshold.
+pub struct Notifier {
+    notifications: Vec<(NotificationConfig, Crossing)>,
+}
+impl Notifier {
+    pub fn new(notifications: &[NotificationConfig]) -> Self {
+        Self {
+            notifications: notifications
+                .iter()
+                .map(|n| {
+                    let threshold = TimeDelta::milliseconds(n.threshold_millis());
+                    (n.clone(), Crossing::new(threshold))
+                })
+                .collect(),
+        }
+    }
+
+    /// Records `remaining` and sends every notification whose threshold it just
+    /// crossed through `sender`, filling its title and body from `values`.
+    pub fn update(
+        &mut self,
+        remaining: TimeDelta,
+        values: &TemplateValues,
+        sender: &dyn NotificationSender,
+    ) {
+        for (notification, crossing) in &mut self.notifications {
+            if crossing.update(remaining) {
+                sender.send(
+                    fill_template(notification.title.as_str(), values).as_str(),
+                    fill_template(notification.body.as_str(), values).as_str(),
+                );
+            }
+        }
+    }
+}
+
+/// The values a notification title or body can refer to.
+pub struct TemplateValues<'a> {
+    pub header: &'a str,
+    pub footer: &'a str,
+    pub remaining: &'a str,
+    pub target: DateTime<FixedOffset>,
+}
+
+/// Replaces `{header}`, `{footer}`, `{remaining}` and `{target}` in `template`.
+/// Other text in braces is kept as written, and the values themselves are never
+/// searched for placeholders.
+pub fn fill_template(template: &str, values: &TemplateValues) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('}').map_or(0, |end| end + 1);
+        let value = match &rest[..end] {
+            "{header}" => values.header.to_string(),
+            "{footer}" => values.footer.to_string(),
+            "{remaining}" => values.remaining.to_string(),
+            "{target}" => values.target.to_rfc3339(),
+            _ => {
+                out.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        out.push_str(value.as_str());
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use utils::TimeUnit;
+
+    fn target() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-12-31T23:59:00+08:00").unwrap()
+    }
+
+    /// Keeps what would have been shown.
+    #[derive(Default)]
+    struct Sent(RefCell<Vec<(String, String)>>);
+    impl NotificationSender for Sent {
+        fn send(&self, title: &str, body: &str) {
+            self.0
+                .borrow_mut()
+                .push((title.to_string(), body.to_string()));
+        }
+    }
+    impl Sent {
+        fn take(&self) -> Vec<(String, String)> {
+            self.0.take()
+        }
+    }
+
+    /// Feeds `notifier` each of `remaining`, in milliseconds, as the refresh would.
+    fn refresh(notifier: &mut Notifier, sent: &Sent, remaining: impl IntoIterator<Item = i64>) {
+        for millis in remaining {
+            let text = format!("{:.1}", millis as f64 / 1000.0);
+            let values = TemplateValues {
+                header: "距离新年",
+                footer: "",
+                remaining: text.as_str(),
+                target: target(),
+            };
+            notifier.update(TimeDelta::milliseconds(millis), &values, sent);
+        }
+    }
+
+    #[test]
+    fn crossing_a_threshold_sends_once() {
+        let mut notifier = Notifier::new(&[
+            NotificationConfig {
+                remaining: 1,
+                unit: TimeUnit::Minute,
+                ..NotificationConfig::default()
+            },
+            NotificationConfig {
+                remaining: 0,
+                unit: TimeUnit::Second,
+                title: "{header}到了".to_string(),
+                body: "{target}".to_string(),
+            },
+        ]);
+        let sent = Sent::default();
+        // From 70s before the target to 5s after it, every 400ms.
+        refresh(&mut notifier, &sent, (-5000..=70_000).rev().step_by(400));
+        assert_eq!(
+            sent.take(),
+            [
+                ("距离新年".to_string(), "剩余 60.0".to_string()),
+                (
+                    "距离新年到了".to_string(),
+                    "2026-12-31T23:59:00+08:00".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn passed_thresholds_wait_for_the_next_crossing() {
+        let mut notifier = Notifier::new(&[NotificationConfig {
+            remaining: 1,
+            unit: TimeUnit::Minute,
+            ..NotificationConfig::default()
+        }]);
+        let sent = Sent::default();
+        // Started with less than a minute to go.
+        refresh(&mut notifier, &sent, [30_000, 29_000]);
+        assert!(sent.take().is_empty());
+        // Reset to 90s, then down past the minute again.
+        refresh(&mut notifier, &sent, [90_000, 60_500, 59_700, 59_000]);
+        assert_eq!(
+            sent.take(),
+            [("距离新年".to_string(), "剩余 59.7".to_string())]
+        );
+    }
+
+    #[test]
+    fn fill_template_replaces_each_placeholder() {
+        let values = TemplateValues {
+            header: "距离新年",
+            footer: "{remaining}",
+            remaining: "5.0",
+            target: target(),
+        };
+        let cases = [
+            ("{header}", "距离新年"),
+            ("剩余 {remaining}", "剩余 5.0"),
+            ("{header}：{remaining} 秒", "距离新年：5.0 秒"),
+            ("{target}", "2026-12-31T23:59:00+08:00"),
+            ("{remaining}{remaining}", "5.05.0"),
+            // Values are inserted as they are.
+            ("{footer}", "{remaining}"),
+            ("{unknown} {header", "{unknown} {header"),
+            ("{{header}}", "{距离新年}"),
+            ("}{", "}{"),
+            ("", ""),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(fill_template(template, &values), expected, "{template}");
+        }
+    }
+}
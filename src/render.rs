@@ -0,0 +1,274 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use config::{Direction, DisplayMode, FinishAction, FinishConfig, TimerSettings};
+use std::time::Duration;
+use utils::{Breakdown, Rounding, TimeUnit, UnitSpan};
+
+/// What is needed to render one countdown.
+pub struct RowSettings {
+    pub target: Result<DateTime<FixedOffset>, String>,
+    pub direction: Direction,
+    precision: u32,
+    rounding: Rounding,
+    unit: TimeUnit,
+    calendar_units: bool,
+    breakdown: Option<Breakdown>,
+    on_finish: FinishConfig,
+}
+impl RowSettings {
+    pub fn from_timer(
+        timer: &TimerSettings,
+        target: Result<DateTime<FixedOffset>, String>,
+        on_finish: &FinishConfig,
+    ) -> Self {
+        Self {
+            target,
+            direction: timer.direction,
+            precision: timer.precision.max(0) as u32,
+            rounding: timer.rounding,
+            unit: timer.unit,
+            calendar_units: timer.calendar_units,
+            breakdown: match timer.display_mode {
+                DisplayMode::Scalar => None,
+                DisplayMode::Breakdown => timer.breakdown_format.parse().ok(),
+            },
+            on_finish: on_finish.clone(),
+        }
+    }
+
+    /// Time left until the target at `now`, for countdowns that have one to reach.
+    /// Timers counting up have none, so their thresholds never fire.
+    pub fn remaining(&self, now: DateTime<FixedOffset>) -> Option<TimeDelta> {
+        match (&self.target, self.direction) {
+            (Ok(target), Direction::Down | Direction::Auto) => Some(*target - now),
+            _ => None,
+        }
+    }
+
+    /// Whether this countdown has reached its target at `now`. Timers counting up
+    /// never finish.
+    pub fn is_finished(&self, now: DateTime<FixedOffset>) -> bool {
+        self.remaining(now).is_some_and(|r| r <= TimeDelta::zero())
+    }
+
+    /// The time to show at `now`. Once a countdown with `direction: down` finishes,
+    /// `on_finish.action` decides what is shown; `auto` timers count up instead.
+    pub fn render(&self, now: DateTime<FixedOffset>) -> Result<String, String> {
+        let target_time = self.target.clone()?;
+        if self.direction != Direction::Down || !self.is_finished(now) {
+            return Ok(self.render_span(now, target_time));
+        }
+        Ok(match self.on_finish.action {
+            FinishAction::Continue | FinishAction::Close => self.render_span(now, target_time),
+            FinishAction::Stop => self.render_span(target_time, target_time),
+            FinishAction::Overdue => format!(
+                "{}{}",
+                self.on_finish.overdue_prefix,
+                self.format_span(now, target_time, true)
+            ),
+            FinishAction::Message => self.on_finish.message.clone(),
+        })
+    }
+
+    fn render_span(
+        &self,
+        now: DateTime<FixedOffset>,
+        target_time: DateTime<FixedOffset>,
+    ) -> String {
+        let counting_up = match self.direction {
+            Direction::Down => false,
+            Direction::Up => true,
+            Direction::Auto => now >= target_time,
+        };
+        self.format_span(now, target_time, counting_up)
+    }
+
+    /// The time left until `target_time`, or the time since it when `counting_up`.
+    fn format_span(
+        &self,
+        now: DateTime<FixedOffset>,
+        target_time: DateTime<FixedOffset>,
+        counting_up: bool,
+    ) -> String {
+        // Counting up mirrors the rounding so that, like a countdown under `ceil`,
+        // the default never shows a value before it is reached.
+        let (from, to, rounding) = if counting_up {
+            let now = now.with_timezone(&target_time.timezone());
+            (target_time, now, self.rounding.mirrored())
+        } else {
+            (now, target_time, self.rounding)
+        };
+        if let Some(breakdown) = &self.breakdown {
+            return utils::format_breakdown(from, to, breakdown, self.calendar_units, rounding);
+        }
+        let span = UnitSpan::new(from, to, self.unit, self.calendar_units);
+        span.format(self.precision, rounding)
+    }
+}
+
+/// How many times a finished countdown blinks when `on_finish.flash` is set.
+pub const FLASH_BLINKS: u32 = 10;
+/// How long each blink lasts.
+pub const FLASH_BLINK_LENGTH: Duration = Duration::from_millis(500);
+
+/// The opacity of a flashing countdown after `blink` blinks: hidden on odd blinks
+/// and shown again on even ones, ending shown.
+pub fn flash_opacity(blink: u32) -> f64 {
+    if blink % 2 == 1 { 0.0 } else { 1.0 }
+}
+
+/// Whether the window should close because a countdown has `just_finished`: `action:
+/// close` waits until every countdown that can finish has finished. `rows` pairs each
+/// countdown with the time it reads, which lags behind for paused durations.
+pub fn should_close<'a>(
+    on_finish: &FinishConfig,
+    rows: impl IntoIterator<Item = (&'a RowSettings, DateTime<FixedOffset>)>,
+    just_finished: bool,
+) -> bool {
+    just_finished
+        && on_finish.action == FinishAction::Close
+        && rows
+            .into_iter()
+            .all(|(row, now)| row.direction == Direction::Up || row.is_finished(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ConfigFile;
+    use utils::Crossing;
+
+    fn at(seconds: i64) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-12-31T23:59:00+08:00").unwrap()
+            + TimeDelta::seconds(seconds)
+    }
+
+    fn row(direction: Direction, action: FinishAction) -> RowSettings {
+        let config = ConfigFile {
+            direction,
+            unit: TimeUnit::Second,
+            precision: 1,
+            on_finish: FinishConfig {
+                action,
+                overdue_prefix: "超时 ".to_string(),
+                message: "时间到".to_string(),
+                flash: false,
+            },
+            ..ConfigFile::default()
+        };
+        let timer = &config.timer_settings()[0];
+        RowSettings::from_timer(timer, Ok(at(0)), &config.on_finish)
+    }
+
+    /// What `row` shows 5s before, at and 5s after its target.
+    fn shown(row: &RowSettings) -> [String; 3] {
+        [-5, 0, 5].map(|seconds| row.render(at(seconds)).unwrap())
+    }
+
+    #[test]
+    fn each_action_renders_after_the_target() {
+        let cases = [
+            (FinishAction::Continue, ["5.0", "0.0", "-5.0"]),
+            (FinishAction::Stop, ["5.0", "0.0", "0.0"]),
+            (FinishAction::Overdue, ["5.0", "超时 0.0", "超时 5.0"]),
+            (FinishAction::Message, ["5.0", "时间到", "时间到"]),
+            (FinishAction::Close, ["5.0", "0.0", "-5.0"]),
+        ];
+        for (action, expected) in cases {
+            assert_eq!(shown(&row(Direction::Down, action)), expected, "{action:?}");
+        }
+    }
+
+    #[test]
+    fn flash_leaves_the_text_alone() {
+        for action in [FinishAction::Continue, FinishAction::Overdue] {
+            let mut flashing = row(Direction::Down, action);
+            flashing.on_finish.flash = true;
+            let steady = row(Direction::Down, action);
+            let mut opacities = Vec::new();
+            for blink in 0..=FLASH_BLINKS {
+                let now = at(0) + FLASH_BLINK_LENGTH * blink;
+                opacities.push(flash_opacity(blink));
+                assert_eq!(
+                    flashing.render(now),
+                    steady.render(now),
+                    "{action:?} {blink}"
+                );
+            }
+            assert!(opacities.contains(&0.0) && opacities.contains(&1.0));
+            assert_eq!(opacities.last(), Some(&1.0));
+        }
+    }
+
+    #[test]
+    fn auto_counts_up_instead_of_applying_the_action() {
+        for action in [FinishAction::Stop, FinishAction::Message] {
+            let auto = row(Direction::Auto, action);
+            assert_eq!(shown(&auto), ["5.0", "0.0", "5.0"]);
+            assert!(!auto.is_finished(at(-1)));
+            assert!(auto.is_finished(at(0)));
+        }
+    }
+
+    #[test]
+    fn up_never_finishes() {
+        let up = row(Direction::Up, FinishAction::Message);
+        assert_eq!(shown(&up), ["-5.0", "0.0", "5.0"]);
+        assert_eq!(up.remaining(at(5)), None);
+        assert!(!up.is_finished(at(5)));
+    }
+
+    #[test]
+    fn invalid_targets_render_their_error() {
+        let mut broken = row(Direction::Down, FinishAction::Continue);
+        broken.target = Err("Invalid target".to_string());
+        assert_eq!(broken.render(at(0)), Err("Invalid target".to_string()));
+        assert_eq!(broken.remaining(at(0)), None);
+    }
+
+    /// Refreshes every 400ms across the target, as the window would.
+    fn finishes(row: &RowSettings) -> Vec<i64> {
+        let mut finish = Crossing::new(TimeDelta::zero());
+        (-3000..3000)
+            .step_by(400)
+            .filter(|&millis| {
+                let now = at(0) + TimeDelta::milliseconds(millis);
+                row.remaining(now).is_some_and(|r| finish.update(r))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finishing_fires_once() {
+        assert_eq!(finishes(&row(Direction::Down, FinishAction::Stop)), [200]);
+        assert_eq!(finishes(&row(Direction::Auto, FinishAction::Stop)), [200]);
+        assert!(finishes(&row(Direction::Up, FinishAction::Stop)).is_empty());
+    }
+
+    #[test]
+    fn close_waits_for_every_countdown() {
+        let on_finish = FinishConfig {
+            action: FinishAction::Close,
+            ..FinishConfig::default()
+        };
+        let down = row(Direction::Down, FinishAction::Close);
+        let auto = RowSettings {
+            target: Ok(at(10)),
+            ..row(Direction::Auto, FinishAction::Close)
+        };
+        let up = row(Direction::Up, FinishAction::Close);
+        let rows = [down, auto, up];
+        let at_all = |seconds| rows.iter().map(move |row| (row, at(seconds)));
+        assert!(!should_close(&on_finish, at_all(0), true));
+        assert!(!should_close(&on_finish, at_all(10), false));
+        assert!(should_close(&on_finish, at_all(10), true));
+        // A paused duration has not reached its target yet.
+        let paused = [(&rows[0], at(10)), (&rows[1], at(9)), (&rows[2], at(10))];
+        assert!(!should_close(&on_finish, paused, true));
+
+        let keep_open = FinishConfig::default();
+        assert!(!should_close(&keep_open, at_all(10), true));
+    }
+}
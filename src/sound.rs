@@ -0,0 +1,38 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use config::SoundConfig;
+use sound::Player;
+use std::path::{Path, PathBuf};
+
+/// Plays the sound files of a config on the main loop, with the volume and sink from
+/// its `sounds` section. Relative paths are taken from the directory of the config
+/// file.
+pub struct SoundPlayer {
+    player: Option<Player>,
+    dir: PathBuf,
+}
+impl SoundPlayer {
+    pub fn new(sounds: &SoundConfig, dir: &Path) -> Self {
+        let player = Player::new(sounds.sink.as_str(), sounds.volume)
+            .map_err(|e| eprintln!("{}", e))
+            .ok();
+        Self {
+            player,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Starts playing `file`, a path or URI; empty names play nothing.
+    pub fn play(&self, file: &str) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        if file.is_empty() {
+            return;
+        }
+        if let Err(e) = sound::resolve_uri(file, &self.dir).and_then(|uri| player.play(&uri)) {
+            eprintln!("{}", e);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (C) 2024 CaSilicate
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::clockmode::change_fontsize;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use config::{ConfigFile, ExportFormat, StopwatchConfig};
+use gtk::prelude::*;
+use gtk::{Label, Window, gdk};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use utils::{Clock, Stopwatch, Ticker, TimeUnit};
+
+/// Shows stopwatch times in the configured unit and precision.
+struct TimeFormat {
+    unit: TimeUnit,
+    precision: i32,
+}
+impl TimeFormat {
+    fn format(&self, time: TimeDelta) -> String {
+        let millis = time.max(TimeDelta::zero()).num_milliseconds() as f64;
+        utils::format_zeros(utils::convert_time_unit(millis, self.unit), self.precision)
+    }
+}
+
+struct StopwatchWindow {
+    window: Window,
+    time: Label,
+    laps: Label,
+    status: Label,
+    clock: Rc<dyn Clock>,
+    stopwatch: RefCell<Stopwatch>,
+    format: TimeFormat,
+    settings: StopwatchConfig,
+}
+impl StopwatchWindow {
+    /// Shows the time elapsed at `now`.
+    fn update(&self, now: DateTime<Utc>) {
+        let elapsed = self.stopwatch.borrow().elapsed(now);
+        let text = self.format.format(elapsed);
+        if self.time.text() != text {
+            self.time.set_text(text.as_str());
+        }
+    }
+
+    /// Lists the latest laps, newest first.
+    fn show_laps(&self) {
+        let laps = self.stopwatch.borrow().laps();
+        let lines: Vec<String> = laps
+            .iter()
+            .rev()
+            .take(self.settings.laps_shown.max(0) as usize)
+            .map(|lap| {
+                format!(
+                    "第 {} 圈  {}  ({})",
+                    lap.number,
+                    self.format.format(lap.lap),
+                    self.format.format(lap.split)
+                )
+            })
+            .collect();
+        self.laps.set_text(lines.join("\n").as_str());
+    }
+
+    fn toggle(&self) {
+        let now = self.clock.now();
+        self.stopwatch.borrow_mut().toggle(now);
+        self.update(now);
+    }
+
+    fn lap(&self) {
+        if self.stopwatch.borrow_mut().lap(self.clock.now()).is_some() {
+            self.show_laps();
+        }
+    }
+
+    fn reset(&self) {
+        let now = self.clock.now();
+        self.stopwatch.borrow_mut().reset(now);
+        self.update(now);
+        self.show_laps();
+    }
+
+    /// Writes every lap to a new file in `export_dir` and reports where.
+    fn export(&self) {
+        let stopwatch = self.stopwatch.borrow();
+        if stopwatch.laps().is_empty() {
+            self.status.set_text("还没有计圈记录");
+            return;
+        }
+        let format = self.settings.export_format;
+        let content = match format {
+            ExportFormat::Csv => stopwatch.to_csv(),
+            ExportFormat::Json => stopwatch.to_json(),
+        };
+        let path = export_path(
+            Path::new(self.settings.export_dir.as_str()),
+            format,
+            self.clock.now().with_timezone(&Local),
+        );
+        match std::fs::write(&path, content) {
+            Ok(()) => self
+                .status
+                .set_text(format!("已导出到 {}", path.display()).as_str()),
+            Err(e) => {
+                eprintln!("Failed to export laps to {}: {}", path.display(), e);
+                self.status.set_text(format!("导出失败：{}", e).as_str());
+            }
+        }
+    }
+}
+
+/// `laps-<date>-<time>.<extension>` in `dir`, stamped with `now`.
+fn export_path(dir: &Path, format: ExportFormat, now: DateTime<Local>) -> PathBuf {
+    let stamp = now.format("%Y%m%d-%H%M%S");
+    dir.join(format!("laps-{}.{}", stamp, format.extension()))
+}
+
+/// Runs the stopwatch window: Space starts and stops, L or Enter records a lap, R
+/// resets and E exports the laps.
+pub fn stopwatch_main(config: &ConfigFile, clock: Rc<dyn Clock>) {
+    gtk::init().unwrap();
+    let glade_src = include_str!("../ui/stopwatch.glade");
+    let builder = gtk::Builder::from_string(glade_src);
+
+    let now = clock.now();
+    let stopwatch = Rc::new(StopwatchWindow {
+        window: builder.object("stopwatch_window").unwrap(),
+        time: builder.object("time").unwrap(),
+        laps: builder.object("laps").unwrap(),
+        status: builder.object("status").unwrap(),
+        clock,
+        stopwatch: RefCell::new(Stopwatch::new(now)),
+        format: TimeFormat {
+            unit: config.unit,
+            precision: config.precision,
+        },
+        settings: config.stopwatch_settings.clone(),
+    });
+    let window = &stopwatch.window;
+    window.set_title(config.window_title.as_str());
+    window.set_size_request(config.window_width, config.window_height);
+    window.set_resizable(false);
+    change_fontsize(&stopwatch.time, config.time_fontsize);
+    change_fontsize(&stopwatch.laps, config.footer_fontsize);
+    stopwatch.update(now);
+
+    window.connect_key_press_event({
+        let stopwatch = stopwatch.clone();
+        move |_, event| {
+            use gdk::keys::constants as key;
+            match event.keyval().to_lower() {
+                key::space => stopwatch.toggle(),
+                key::l | key::Return | key::KP_Enter => stopwatch.lap(),
+                key::r => stopwatch.reset(),
+                key::e => stopwatch.export(),
+                _ => return gtk::glib::Propagation::Proceed,
+            }
+            gtk::glib::Propagation::Stop
+        }
+    });
+
+    let _ticker = Ticker::start(
+        Duration::from_millis(config.interval as u64),
+        stopwatch.clock.clone(),
+        {
+            let stopwatch = stopwatch.clone();
+            move |now| stopwatch.update(now)
+        },
+    );
+
+    window.connect_destroy(move |_| gtk::main_quit());
+    window.show_all();
+    gtk::main();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn export_files_are_named_after_the_clock() {
+        let now = Local.with_ymd_and_hms(2026, 12, 31, 23, 59, 5).unwrap();
+        assert_eq!(
+            export_path(Path::new("laps"), ExportFormat::Csv, now),
+            Path::new("laps/laps-20261231-235905.csv")
+        );
+        assert_eq!(
+            export_path(Path::new(""), ExportFormat::Json, now),
+            Path::new("laps-20261231-235905.json")
+        );
+    }
+}